@@ -0,0 +1,73 @@
+use crate::dll_classifier::DllClassification;
+use crate::dll_management::InstallPlan;
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::error;
+
+/// Machine-readable results of the CLI commands, printed as one JSON object per line with `--json`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CliOutput {
+    Classify {
+        path: PathBuf,
+        #[serde(flatten)]
+        classification: Option<ClassificationOutput>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    DryRun {
+        game_dir: PathBuf,
+        target: PathBuf,
+        current: Option<ClassificationOutput>,
+        backup: Option<PathBuf>,
+    },
+    SwitchVersion {
+        version: String,
+        target: PathBuf,
+        backup: Option<PathBuf>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClassificationOutput {
+    classification: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+impl From<DllClassification> for ClassificationOutput {
+    fn from(classification: DllClassification) -> Self {
+        let (name, version) = match classification {
+            DllClassification::Invalid => ("invalid", None),
+            DllClassification::NonDe => ("non_de", None),
+            DllClassification::Vanilla => ("vanilla", None),
+            DllClassification::Rando(version) => ("rando", Some(version.to_string())),
+            DllClassification::UnknownRando(_) => ("unknown_rando", None),
+        };
+        Self {
+            classification: name,
+            version,
+        }
+    }
+}
+
+impl CliOutput {
+    pub fn dry_run(game_dir: PathBuf, plan: InstallPlan) -> Self {
+        CliOutput::DryRun {
+            game_dir,
+            target: plan.target,
+            current: plan.target_classification.map(Into::into),
+            backup: plan.backup,
+        }
+    }
+
+    pub fn print(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{json}"),
+            Err(err) => error!(?err, ?self, "Couldn't serialize CLI output"),
+        }
+    }
+}
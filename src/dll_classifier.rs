@@ -1,9 +1,13 @@
-use crate::dll_parser::parse_dll;
+use crate::dll_parser::{ParseError, parse_dll};
 use memchr::memmem;
 use regex::bytes::Regex;
-use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter, Write};
+use std::fs::File;
 use std::hash::{DefaultHasher, Hasher};
 use std::io;
+use std::io::Read;
 use std::path::Path;
 use std::sync::LazyLock;
 use tracing::{debug, info_span, instrument};
@@ -17,13 +21,28 @@ pub enum DllClassification {
     UnknownRando(u64),
 }
 
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
 pub struct RandoVersion {
     pub major: u32,
     pub minor: u32,
     pub patch: u32,
 }
 
+impl RandoVersion {
+    /// Parses versions like "1.2.3" or "v1.2.3", surrounding whitespace is ignored.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let mut parts = text.strip_prefix('v').unwrap_or(text).split('.');
+        let mut next_part = || parts.next()?.parse().ok();
+        let version = Self {
+            major: next_part()?,
+            minor: next_part()?,
+            patch: next_part()?,
+        };
+        parts.next().is_none().then_some(version)
+    }
+}
+
 impl Display for RandoVersion {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let &Self {
@@ -35,39 +54,111 @@ impl Display for RandoVersion {
     }
 }
 
+/// Why a file was classified as [`DllClassification::Invalid`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, thiserror::Error)]
+pub enum ClassifyError {
+    /// The file is not a valid .NET dll, with the reason and offset from the parser.
+    #[error("Invalid dll: {0}")]
+    InvalidDll(#[from] ParseError),
+    /// The file is a valid .NET dll, but not an Ori assembly.
+    #[error("Not an Ori assembly")]
+    NotOri,
+}
+
 #[instrument]
 pub fn classify_dll_file(path: &Path) -> io::Result<DllClassification> {
     let file = info_span!("open_file").in_scope(|| std::fs::File::open(path))?;
+
+    // Most files next to the game assembly aren't .NET dlls at all, they're rejected without mapping them
+    let mut header = Vec::with_capacity(HEADER_PRECHECK_SIZE);
+    (&file)
+        .take(HEADER_PRECHECK_SIZE as u64)
+        .read_to_end(&mut header)?;
+    if !has_cli_header(&header) {
+        debug!("No .NET header");
+        return Ok(DllClassification::Invalid);
+    }
+
+    // Parsing only reads the headers and the heaps, so the game's other assemblies are rejected quickly too
     let data = info_span!("mmap_file").in_scope(|| unsafe { memmap2::Mmap::map(&file) })?;
     Ok(classify_dll(&data))
 }
 
-#[instrument(skip(file_data))]
-pub fn classify_dll(file_data: &[u8]) -> DllClassification {
-    let heaps = match parse_dll(file_data) {
-        Ok(heaps) => heaps,
-        Err(e) => {
-            debug!(?e, "Invalid Dll");
-            return DllClassification::Invalid;
+/// The PE headers are at the start of the file, this is plenty for them.
+const HEADER_PRECHECK_SIZE: usize = 4096;
+/// Only the Definitive Edition contains this class.
+const DE_MARKER: &[u8] = b"SpiritGrenadeDamageDealer\0";
+/// Only the original game contains this class.
+const NON_DE_MARKER: &[u8] = b"HoldingNightberryCondition\0";
+/// The randomizer adds classes in this namespace.
+const RANDO_MARKER: &[u8] = b"Randomizer\0";
+
+/// Whether `header`, the start of a file, is a PE file with a .NET (CLI) header.
+/// Doesn't validate anything else, that's left to the full parse.
+fn has_cli_header(header: &[u8]) -> bool {
+    let read_u16 = |offset: usize| {
+        Some(u16::from_le_bytes(
+            header.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let read_u32 = |offset: usize| {
+        Some(u32::from_le_bytes(
+            header.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    let check = || -> Option<bool> {
+        if header.get(0..2)? != b"MZ" {
+            return Some(false);
+        }
+        let pe_header = read_u32(60)? as usize;
+        if header.get(pe_header..pe_header + 4)? != b"PE\0\0" {
+            return Some(false);
         }
+
+        // The data directories follow the optional header fields, which are longer for 64 bit images
+        let optional_header = pe_header + 24;
+        let data_directories = match read_u16(optional_header)? {
+            0x10b => optional_header + 96,
+            0x20b => optional_header + 112,
+            _ => return Some(false),
+        };
+        // The CLI header is data directory 14, each entry is an RVA and a size
+        let cli_header = data_directories + 14 * 8;
+        Some(read_u32(cli_header)? != 0 && read_u32(cli_header + 4)? != 0)
     };
+    check().unwrap_or(false)
+}
+
+#[instrument(skip(file_data))]
+pub fn classify_dll(file_data: &[u8]) -> DllClassification {
+    classify_bytes(file_data).unwrap_or_else(|e| {
+        debug!(?e, "Invalid Dll");
+        DllClassification::Invalid
+    })
+}
+
+/// Like [`classify_dll`], but reports why a file is invalid instead of returning [`DllClassification::Invalid`].
+#[instrument(skip(file_data))]
+pub fn classify_bytes(file_data: &[u8]) -> Result<DllClassification, ClassifyError> {
+    let heaps = parse_dll(file_data)?;
 
-    if memmem::find(heaps.strings, b"SpiritGrenadeDamageDealer\0").is_none() {
-        return if memmem::find(heaps.strings, b"HoldingNightberryCondition\0").is_some() {
-            DllClassification::NonDe
+    if memmem::find(heaps.strings, DE_MARKER).is_none() {
+        return if memmem::find(heaps.strings, NON_DE_MARKER).is_some() {
+            Ok(DllClassification::NonDe)
         } else {
-            DllClassification::Invalid
+            Err(ClassifyError::NotOri)
         };
     }
 
-    if memmem::find(heaps.strings, b"Randomizer\0").is_none() {
-        return DllClassification::Vanilla;
+    if memmem::find(heaps.strings, RANDO_MARKER).is_none() {
+        return Ok(DllClassification::Vanilla);
     }
 
-    if let Some(v) = extract_rando_version(heaps.us) {
-        DllClassification::Rando(v)
+    if let Some(v) = heaps.us.and_then(extract_rando_version) {
+        Ok(DllClassification::Rando(v))
     } else {
-        DllClassification::UnknownRando(compute_hash(file_data))
+        Ok(DllClassification::UnknownRando(compute_hash(file_data)))
     }
 }
 
@@ -77,16 +168,55 @@ fn compute_hash(value: &[u8]) -> u64 {
     hasher.finish()
 }
 
+/// SHA-256 of the file as lowercase hex, to compare builds against known-good ones.
+/// Unlike [`compute_hash`], this is stable across Rust versions and machines.
+#[instrument]
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    let hex = hasher
+        .finalize()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    Ok(hex)
+}
+
+/// Byte order of the UTF-16 strings in the `#US` heap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum ByteOrder {
+    Little,
+    Big,
+}
+
+/// Finds the version string literal, e.g. "1.2.34".
+/// String literals are UTF-16LE, but big endian is recognized too in case a build stores them differently.
 #[instrument(skip_all)]
 fn extract_rando_version(us_heap: &[u8]) -> Option<RandoVersion> {
-    static VERSION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-        // Regex to find a version string literal embedded into the dll, e.g. "1.2.34"
-        // Format for a version string: <length prefix><version string><nul byte>
-        // Format for <version string>: UTF-16 encoded [\d+ '.' \d+ '.' \d+]
+    extract_rando_version_in(us_heap, ByteOrder::Little)
+        .or_else(|| extract_rando_version_in(us_heap, ByteOrder::Big))
+}
+
+fn extract_rando_version_in(us_heap: &[u8], byte_order: ByteOrder) -> Option<RandoVersion> {
+    // Format for a version string: <length prefix><version string><nul byte>
+    // Format for <version string>: UTF-16 encoded [\d+ '.' \d+ '.' \d+]
+    static LITTLE_ENDIAN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
         Regex::new(r"(?-u).((?:\d\x00)+)\.\x00((?:\d\x00)+)\.\x00((?:\d\x00)+)\x00").unwrap()
     });
+    static BIG_ENDIAN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?-u).((?:\x00\d)+)\x00\.((?:\x00\d)+)\x00\.((?:\x00\d)+)\x00").unwrap()
+    });
+
+    let regex = match byte_order {
+        ByteOrder::Little => &LITTLE_ENDIAN_REGEX,
+        ByteOrder::Big => &BIG_ENDIAN_REGEX,
+    };
 
-    VERSION_REGEX
+    regex
         .captures_iter(us_heap)
         .filter_map(|m| {
             let (full, [major, minor, patch]) = m.extract();
@@ -94,9 +224,9 @@ fn extract_rando_version(us_heap: &[u8]) -> Option<RandoVersion> {
             let length_prefix = full[0];
             if length_prefix as usize == full.len() - 1 {
                 Some(RandoVersion {
-                    major: parse_trusted_utf16_number(major)?,
-                    minor: parse_trusted_utf16_number(minor)?,
-                    patch: parse_trusted_utf16_number(patch)?,
+                    major: parse_utf16_number(major, byte_order)?,
+                    minor: parse_utf16_number(minor, byte_order)?,
+                    patch: parse_utf16_number(patch, byte_order)?,
                 })
             } else {
                 None
@@ -105,13 +235,209 @@ fn extract_rando_version(us_heap: &[u8]) -> Option<RandoVersion> {
         .max()
 }
 
-fn parse_trusted_utf16_number(bytes: &[u8]) -> Option<u32> {
+/// Parses a UTF-16 encoded decimal number.
+/// Returns `None` if any code unit isn't an ASCII digit, or if the number overflows.
+fn parse_utf16_number(bytes: &[u8], byte_order: ByteOrder) -> Option<u32> {
+    let code_units = bytes.chunks_exact(2);
+    if bytes.is_empty() || !code_units.remainder().is_empty() {
+        return None;
+    }
+
     let mut number = 0u32;
 
-    for i in (0..bytes.len()).step_by(2) {
-        let digit = u32::from(bytes[i] - b'0');
+    for code_unit in code_units {
+        let (low, high) = match byte_order {
+            ByteOrder::Little => (code_unit[0], code_unit[1]),
+            ByteOrder::Big => (code_unit[1], code_unit[0]),
+        };
+        if high != 0 || !low.is_ascii_digit() {
+            return None;
+        }
+        let digit = u32::from(low - b'0');
         number = number.checked_mul(10)?.checked_add(digit)?;
     }
 
     Some(number)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{dotnet_dll, rando_dll, us_heap, vanilla_dll};
+
+    const PE_OFFSET: usize = 0x80;
+    const OPTIONAL_HEADER: usize = PE_OFFSET + 24;
+
+    /// A header of a PE file with the optional header `magic`, the CLI header data directory is set if `cli` is.
+    fn pe_header(magic: u16, cli: bool) -> Vec<u8> {
+        let mut header = vec![0; 0x400];
+        header[0..2].copy_from_slice(b"MZ");
+        header[60..64].copy_from_slice(&u32::try_from(PE_OFFSET).unwrap().to_le_bytes());
+        header[PE_OFFSET..PE_OFFSET + 4].copy_from_slice(b"PE\0\0");
+        header[OPTIONAL_HEADER..OPTIONAL_HEADER + 2].copy_from_slice(&magic.to_le_bytes());
+        if cli {
+            let entry = cli_header_entry(magic);
+            header[entry..entry + 4].copy_from_slice(&0x2008u32.to_le_bytes());
+            header[entry + 4..entry + 8].copy_from_slice(&0x48u32.to_le_bytes());
+        }
+        header
+    }
+
+    fn cli_header_entry(magic: u16) -> usize {
+        let data_directories = match magic {
+            0x10b => OPTIONAL_HEADER + 96,
+            _ => OPTIONAL_HEADER + 112,
+        };
+        data_directories + 14 * 8
+    }
+
+    #[test]
+    fn accepts_pe32_with_cli_header() {
+        assert!(has_cli_header(&pe_header(0x10b, true)));
+    }
+
+    #[test]
+    fn accepts_pe32_plus_with_cli_header() {
+        assert!(has_cli_header(&pe_header(0x20b, true)));
+    }
+
+    #[test]
+    fn rejects_missing_cli_header() {
+        assert!(!has_cli_header(&pe_header(0x10b, false)));
+        assert!(!has_cli_header(&pe_header(0x20b, false)));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(!has_cli_header(&[]));
+        assert!(!has_cli_header(b"not a dll at all"));
+
+        let mut unknown_magic = pe_header(0x10b, true);
+        unknown_magic[OPTIONAL_HEADER] = 0x07;
+        assert!(!has_cli_header(&unknown_magic));
+
+        let mut no_pe_signature = pe_header(0x10b, true);
+        no_pe_signature[PE_OFFSET] = b'X';
+        assert!(!has_cli_header(&no_pe_signature));
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        for magic in [0x10b, 0x20b] {
+            let header = pe_header(magic, true);
+            let entry = cli_header_entry(magic);
+            for len in [
+                2,
+                62,
+                PE_OFFSET + 2,
+                OPTIONAL_HEADER + 1,
+                entry + 2,
+                entry + 6,
+            ] {
+                assert!(!has_cli_header(&header[..len]), "length {len}");
+            }
+            assert!(has_cli_header(&header[..entry + 8]));
+        }
+    }
+
+    const VERSION: RandoVersion = RandoVersion {
+        major: 4,
+        minor: 2,
+        patch: 17,
+    };
+
+    #[test]
+    fn extracts_little_endian_version() {
+        let heap = us_heap(&["Randomizer", "4.2.17"], ByteOrder::Little);
+        assert_eq!(extract_rando_version(&heap), Some(VERSION));
+    }
+
+    #[test]
+    fn extracts_big_endian_version() {
+        let heap = us_heap(&["Randomizer", "4.2.17"], ByteOrder::Big);
+        assert_eq!(extract_rando_version(&heap), Some(VERSION));
+        assert_eq!(extract_rando_version_in(&heap, ByteOrder::Little), None);
+    }
+
+    #[test]
+    fn picks_highest_version() {
+        let heap = us_heap(&["1.0.0", "4.2.17", "4.2.3"], ByteOrder::Little);
+        assert_eq!(extract_rando_version(&heap), Some(VERSION));
+    }
+
+    #[test]
+    fn ignores_wrong_length_prefix() {
+        for byte_order in [ByteOrder::Little, ByteOrder::Big] {
+            let mut heap = us_heap(&["4.2.17"], byte_order);
+            // The length prefix of the entry, after the empty string
+            heap[1] += 2;
+            assert_eq!(extract_rando_version(&heap), None);
+        }
+    }
+
+    #[test]
+    fn ignores_heap_without_version() {
+        let heap = us_heap(&["Randomizer", "4.2"], ByteOrder::Little);
+        assert_eq!(extract_rando_version(&heap), None);
+        assert_eq!(extract_rando_version(&[]), None);
+    }
+
+    #[test]
+    fn parses_utf16_numbers() {
+        assert_eq!(
+            parse_utf16_number(b"4\x002\x00", ByteOrder::Little),
+            Some(42)
+        );
+        assert_eq!(parse_utf16_number(b"\x004\x002", ByteOrder::Big), Some(42));
+        assert_eq!(parse_utf16_number(b"", ByteOrder::Little), None);
+        assert_eq!(parse_utf16_number(b"4\x002", ByteOrder::Little), None);
+        assert_eq!(parse_utf16_number(b"4\x01", ByteOrder::Little), None);
+        assert_eq!(
+            parse_utf16_number(
+                &us_heap(&["99999999999"], ByteOrder::Little)[2..24],
+                ByteOrder::Little
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_utf16_numbers() {
+        for byte_order in [ByteOrder::Little, ByteOrder::Big] {
+            // Digits of other scripts, a character with an ASCII digit as its low byte, and non-digits
+            for text in ["\u{0664}2", "4\u{FF12}", "\u{0134}", "4a", "4 2", "-4"] {
+                // Only the string of the entry, without the empty string, length prefix and terminal byte
+                let heap = us_heap(&[text], byte_order);
+                let bytes = &heap[2..heap.len() - 1];
+                assert_eq!(parse_utf16_number(bytes, byte_order), None, "{text:?}");
+            }
+        }
+        // The other byte order has the digits in the high bytes
+        assert_eq!(parse_utf16_number(b"4\x002\x00", ByteOrder::Big), None);
+        assert_eq!(parse_utf16_number(b"\x004\x002", ByteOrder::Little), None);
+    }
+
+    #[test]
+    fn classifies_game_assemblies() {
+        assert_eq!(classify_dll(&vanilla_dll()), DllClassification::Vanilla);
+        assert_eq!(
+            classify_dll(&rando_dll("4.2.17")),
+            DllClassification::Rando(VERSION)
+        );
+    }
+
+    #[test]
+    fn classifies_assemblies_without_us_heap() {
+        let vanilla = dotnet_dll(b"\0SpiritGrenadeDamageDealer\0", None, 0);
+        assert_eq!(classify_dll(&vanilla), DllClassification::Vanilla);
+
+        let rando = dotnet_dll(b"\0SpiritGrenadeDamageDealer\0Randomizer\0", None, 0);
+        assert_eq!(
+            classify_dll(&rando),
+            DllClassification::UnknownRando(compute_hash(&rando))
+        );
+
+        let other = dotnet_dll(b"\0<Module>\0", None, 0);
+        assert_eq!(classify_bytes(&other), Err(ClassifyError::NotOri));
+    }
+}
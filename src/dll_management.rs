@@ -1,33 +1,48 @@
 use crate::dll_classifier::{DllClassification, RandoVersion, classify_dll, classify_dll_file};
-use crate::game::GameDir;
+use crate::game::{GameDir, NetworkLocationUnavailable};
+use crate::paths::extended_length_path;
+use crate::settings::app_data_dir;
 use color_eyre::Result;
-use color_eyre::eyre::WrapErr;
+use color_eyre::eyre::{OptionExt, WrapErr, eyre};
+use color_eyre::{Section, SectionExt};
 use rand::distr::{Alphanumeric, SampleString};
 use rayon::iter::ParallelBridge;
 use rayon::iter::ParallelIterator;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::borrow::Cow;
-use std::fmt::{Display, Formatter};
-use std::fs::read_dir;
+use std::cmp::Reverse;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter, Write};
+use std::fs::{File, ReadDir, read_dir};
+use std::io;
 use std::io::ErrorKind;
 use std::mem;
+use std::os::windows::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
-use tracing::{Span, debug, error, info, instrument};
+use std::ptr;
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{Span, debug, error, info, instrument, warn};
+use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct OriDll {
     pub kind: OriDllKind,
     pub path: PathBuf,
     pub display_name: String,
+    /// Modified time of the file, the installer keeps it when copying dlls.
+    pub modified: Option<SystemTime>,
 }
 
 impl OriDll {
-    fn new(path: PathBuf, classification: DllClassification) -> Option<Self> {
-        let kind = match classification {
-            DllClassification::Invalid | DllClassification::NonDe => return None,
-            DllClassification::Vanilla => OriDllKind::Vanilla,
-            DllClassification::Rando(v) => OriDllKind::Rando(v),
-            DllClassification::UnknownRando(hash) => OriDllKind::UnknownRando(hash),
-        };
+    fn new(
+        path: PathBuf,
+        classification: DllClassification,
+        modified: Option<SystemTime>,
+    ) -> Option<Self> {
+        let kind = OriDllKind::from_classification(classification)?;
 
         let display_name = path
             .file_name()
@@ -39,8 +54,14 @@ impl OriDll {
             kind,
             path,
             display_name,
+            modified,
         })
     }
+
+    /// File name for copies outside the game dir, naming the version the file contains.
+    pub fn export_name(&self) -> String {
+        self.kind.export_name()
+    }
 }
 
 impl Display for OriDll {
@@ -55,6 +76,53 @@ impl Display for OriDll {
     }
 }
 
+/// Where backups of replaced and newly installed dlls are created.
+/// Both places are always searched for dlls, no matter which is configured.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BackupLocation {
+    /// Next to the active dll, where backups always used to be.
+    #[default]
+    Managed,
+    /// In the app's own data directory, so the game dir isn't bloated with backups.
+    AppData,
+}
+
+impl BackupLocation {
+    pub const ALL: [BackupLocation; 2] = [BackupLocation::Managed, BackupLocation::AppData];
+
+    fn dir(self, managed: &Path) -> Result<PathBuf, InstallError> {
+        match self {
+            BackupLocation::Managed => Ok(managed.to_owned()),
+            BackupLocation::AppData => app_data_backup_dir(managed)
+                .map_err(|err| InstallError::AppDataUnavailable(err.into())),
+        }
+    }
+}
+
+/// Backups of the game dir with the Managed directory `managed` that are stored outside of it,
+/// see [`BackupLocation::AppData`].
+///
+/// Every game dir has its own directory, named after a hash of its resolved Managed path,
+/// so the dlls of one install never count as backups of another one.
+fn app_data_backup_dir(managed: &Path) -> Result<PathBuf> {
+    Ok(app_data_dir()?
+        .join("backups")
+        .join(backup_dir_name(managed)))
+}
+
+/// Paths are case-insensitive, so differently cased spellings of the same path share a directory.
+/// The hash has to stay the same across app versions, which [`DefaultHasher`](std::hash::DefaultHasher) doesn't.
+fn backup_dir_name(managed: &Path) -> String {
+    let path = managed.to_string_lossy().to_lowercase();
+    Sha256::digest(path.as_bytes()).iter().take(8).fold(
+        String::with_capacity(16),
+        |mut hex, byte| {
+            _ = write!(hex, "{byte:02x}");
+            hex
+        },
+    )
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum OriDllKind {
     Vanilla,
@@ -62,49 +130,438 @@ pub enum OriDllKind {
     UnknownRando(u64),
 }
 
+impl OriDllKind {
+    /// The kind of an Ori DE dll, `None` for anything else.
+    pub fn from_classification(classification: DllClassification) -> Option<Self> {
+        match classification {
+            DllClassification::Invalid | DllClassification::NonDe => None,
+            DllClassification::Vanilla => Some(OriDllKind::Vanilla),
+            DllClassification::Rando(v) => Some(OriDllKind::Rando(v)),
+            DllClassification::UnknownRando(hash) => Some(OriDllKind::UnknownRando(hash)),
+        }
+    }
+
+    /// Like [`OriDll::export_name`], for dlls that aren't stored anywhere yet, e.g. downloads.
+    pub fn export_name(self) -> String {
+        match self {
+            OriDllKind::Vanilla => "Assembly-CSharp.vanilla.dll".to_owned(),
+            OriDllKind::Rando(v) => format!("Assembly-CSharp.rando.{v}.dll"),
+            OriDllKind::UnknownRando(hash) => {
+                format!("Assembly-CSharp.rando-custom.{hash:016x}.dll")
+            }
+        }
+    }
+}
+
+/// Why installing a dll failed.
+/// Running out of disk space or losing the network share can be fixed by the user, so callers match on them.
+#[derive(Debug, thiserror::Error)]
+pub enum InstallError {
+    #[error(transparent)]
+    NotEnoughDiskSpace(#[from] NotEnoughDiskSpace),
+    #[error(transparent)]
+    NetworkLocationUnavailable(#[from] NetworkLocationUnavailable),
+    #[error("Failed to classify target {path:?}")]
+    ClassifyTarget {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Couldn't locate the app data directory for backups")]
+    AppDataUnavailable(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("Error creating backup directory {path:?}")]
+    CreateBackupDir {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Error creating backup {path:?}")]
+    Backup {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Error installing dll to {path:?}")]
+    Install {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Filesystem operations of the install logic,
+/// so the backup decisions don't depend on how the Managed directory is accessed.
+trait DllFs {
+    /// Copies `from` to `to`, keeping the modified time of `from`.
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn exists(&self, path: &Path) -> io::Result<bool>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    fn classify(&self, path: &Path) -> io::Result<DllClassification>;
+}
+
+struct RealFs;
+
+impl DllFs for RealFs {
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::copy(from, to)?;
+
+        if let Err(err) = copy_modified_time(from, to) {
+            warn!(?err, "Couldn't preserve modified time of copied dll");
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        move_file(from, to)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        std::fs::exists(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    fn classify(&self, path: &Path) -> io::Result<DllClassification> {
+        classify_dll_file(path)
+    }
+}
+
+/// Serializes scans and changes of the Managed directory, so a scan never sees an install halfway done.
+/// Only one game dir is in use at a time, so a single lock for all of them suffices.
+static MANAGED_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+/// Held by all public functions of this module that scan or change the Managed directory.
+/// They don't call each other, so the lock is never taken twice by the same thread.
+pub fn lock_managed_dir() -> MutexGuard<'static, ()> {
+    // The lock doesn't protect any data, so a panic while holding it can't leave anything inconsistent
+    MANAGED_DIR_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
 #[instrument(skip(all_dlls, to_install), fields(to_install.path=?to_install.path))]
-pub fn install_dll(game_dir: &GameDir, to_install: &OriDll, all_dlls: &[OriDll]) -> Result<()> {
-    let target = prepare_target(game_dir, all_dlls)?;
+pub fn install_dll(
+    game_dir: &GameDir,
+    to_install: &OriDll,
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    game_dir.check_reachable()?;
+    let _lock = lock_managed_dir();
+    install_dll_with(&RealFs, game_dir, to_install, all_dlls, backup_location)
+}
+
+fn install_dll_with(
+    fs: &impl DllFs,
+    game_dir: &GameDir,
+    to_install: &OriDll,
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    let plan = prepare_target(fs, game_dir, all_dlls, backup_location)?;
+    let target = &plan.target;
 
     info!(?target, "Copying/Installing dll");
-    std::fs::copy(&to_install.path, target).wrap_err("Error copying dll")?;
+    fs.copy(&to_install.path, target)
+        .map_err(|source| InstallError::Install {
+            path: target.clone(),
+            source,
+        })?;
 
-    Ok(())
+    Ok(plan)
+}
+
+/// Sets the modified time of `to` to the one of `from`,
+/// so the installed dll keeps the timestamp of the build it was copied from.
+#[instrument]
+fn copy_modified_time(from: &Path, to: &Path) -> io::Result<()> {
+    let modified = std::fs::metadata(from)?.modified()?;
+    File::options().write(true).open(to)?.set_modified(modified)
 }
 
 #[instrument(skip(dll, all_dlls))]
-pub fn install_new_dll(game_dir: &GameDir, dll: &[u8], all_dlls: &[OriDll]) -> Result<()> {
-    let target = prepare_target(game_dir, all_dlls)?;
+pub fn install_new_dll(
+    game_dir: &GameDir,
+    dll: &[u8],
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    game_dir.check_reachable()?;
+    let _lock = lock_managed_dir();
+    install_new_dll_with(&RealFs, game_dir, dll, all_dlls, backup_location)
+}
+
+fn install_new_dll_with(
+    fs: &impl DllFs,
+    game_dir: &GameDir,
+    dll: &[u8],
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    let plan = prepare_target(fs, game_dir, all_dlls, backup_location)?;
+    let target = &plan.target;
 
     info!(?target, "Installing dll");
-    std::fs::write(target, dll).wrap_err("Error writing dll")?;
+    fs.write(target, dll)
+        .map_err(|source| InstallError::Install {
+            path: target.clone(),
+            source,
+        })?;
 
-    let classification = classify_dll(dll);
-    let backup_name = unique_name_for_dll(&game_dir.managed, classification);
-    if let Err(err) = std::fs::write(backup_name, dll) {
+    let backup = backup_location
+        .dir(&game_dir.resolved_managed())
+        .and_then(|backup_dir| {
+            fs.create_dir_all(&backup_dir)
+                .map_err(|source| InstallError::CreateBackupDir {
+                    path: backup_dir.clone(),
+                    source,
+                })?;
+            let backup_name = unique_name_for_dll(fs, &backup_dir, classify_dll(dll));
+            fs.write(&backup_name, dll)
+                .map_err(|source| InstallError::Backup {
+                    path: backup_name,
+                    source,
+                })
+        });
+    if let Err(err) = backup {
         error!(?err, "Couldn't create immediate backup");
     }
 
+    Ok(plan)
+}
+
+const MB: u64 = 1024 * 1024;
+
+/// The volume of the Managed directory doesn't have enough room for an install.
+#[derive(Debug, Copy, Clone, thiserror::Error)]
+#[error(
+    "Not enough disk space: {} MB required, {} MB available",
+    .required.div_ceil(MB),
+    .available / MB
+)]
+pub struct NotEnoughDiskSpace {
+    pub required: u64,
+    pub available: u64,
+}
+
+/// Fails early if there's no room for installing a dll of `dll_size` bytes,
+/// which needs space for the dll, its immediate backup and a backup of the active dll.
+#[instrument(skip(game_dir))]
+pub fn check_disk_space(game_dir: &GameDir, dll_size: u64) -> Result<(), InstallError> {
+    game_dir.check_reachable()?;
+    let managed = game_dir.resolved_managed();
+    let active_size = std::fs::metadata(managed.join("Assembly-CSharp.dll"))
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let required = dll_size * 2 + active_size;
+
+    let available = match free_disk_space(&managed) {
+        Ok(available) => available,
+        Err(err) => {
+            // Not being able to check shouldn't prevent installing
+            warn!(?err, "Couldn't check free disk space");
+            return Ok(());
+        }
+    };
+    debug!(required, available, "Checked disk space");
+
+    if available < required {
+        return Err(InstallError::NotEnoughDiskSpace(NotEnoughDiskSpace {
+            required,
+            available,
+        }));
+    }
+
     Ok(())
 }
 
+/// Size of the largest known dll, as estimate for the size of a dll that is yet to be downloaded.
+pub fn largest_dll_size(all_dlls: &[OriDll]) -> u64 {
+    all_dlls
+        .iter()
+        .filter_map(|dll| std::fs::metadata(&dll.path).ok())
+        .map(|metadata| metadata.len())
+        .max()
+        .unwrap_or(0)
+}
+
+#[instrument]
+fn free_disk_space(path: &Path) -> io::Result<u64> {
+    let wide_path: Vec<u16> = extended_length_path(path)
+        .as_os_str()
+        .encode_wide()
+        .chain([0])
+        .collect();
+    let mut available = 0u64;
+
+    let success = unsafe {
+        GetDiskFreeSpaceExW(
+            wide_path.as_ptr(),
+            &raw mut available,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    if success == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(available)
+}
+
+/// What installing a dll would do to the Managed directory.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InstallPlan {
+    /// The path the dll gets installed to.
+    pub target: PathBuf,
+    /// Classification of the dll currently at `target`, `None` if there is none.
+    pub target_classification: Option<DllClassification>,
+    /// Where the dll currently at `target` gets moved to, `None` if no backup is needed.
+    pub backup: Option<PathBuf>,
+}
+
+impl InstallPlan {
+    /// The vanilla backup this plan creates, if any.
+    pub fn vanilla_backup(&self) -> Option<&Path> {
+        match self.target_classification {
+            Some(DllClassification::Vanilla) => self.backup.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for InstallPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "target: {}", self.target.display())?;
+        match self.target_classification {
+            Some(classification) => writeln!(f, "current: {classification:?}")?,
+            None => writeln!(f, "current: <none>")?,
+        }
+        match &self.backup {
+            Some(backup) => writeln!(f, "backup: {}", backup.display()),
+            None => writeln!(f, "backup: <none>"),
+        }
+    }
+}
+
+/// Computes what [`install_dll`] or [`install_new_dll`] would do, without touching any files.
+///
+/// If the backup name needs a random suffix, the actual install will use a different suffix.
 #[instrument(skip(game_dir, all_dlls))]
-fn prepare_target(game_dir: &GameDir, all_dlls: &[OriDll]) -> Result<PathBuf> {
-    let target = game_dir.managed.join("Assembly-CSharp.dll");
+pub fn plan_install(
+    game_dir: &GameDir,
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    let _lock = lock_managed_dir();
+    plan_install_with(&RealFs, game_dir, all_dlls, backup_location)
+}
+
+fn plan_install_with(
+    fs: &impl DllFs,
+    game_dir: &GameDir,
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    let managed = game_dir.resolved_managed();
+    let target = managed.join("Assembly-CSharp.dll");
 
-    let target_classification = match classify_dll_file(&target) {
+    let target_classification = match fs.classify(&target) {
         Ok(classification) => classification,
-        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(target),
-        Err(err) => return Err(err).wrap_err("Failed to classify target"),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Ok(InstallPlan {
+                target,
+                target_classification: None,
+                backup: None,
+            });
+        }
+        Err(source) => {
+            return Err(InstallError::ClassifyTarget {
+                path: target,
+                source,
+            });
+        }
     };
 
-    if should_backup_target(&target, target_classification, all_dlls) {
-        let new_name = unique_name_for_dll(&game_dir.managed, target_classification);
-        info!(install_target=?target, ?new_name, "Renaming dll as backup");
-        std::fs::rename(&target, new_name).wrap_err("Error creating backup")?;
+    let backup_needed = if target_classification == DllClassification::Vanilla {
+        // Don't trust the possibly outdated list for vanilla,
+        // a pristine copy has to exist before the first modification
+        !all_dlls.iter().any(|dll| {
+            dll.kind == OriDllKind::Vanilla
+                && dll.path != target
+                && fs.exists(&dll.path).unwrap_or(false)
+        })
+    } else {
+        should_backup_target(&target, target_classification, all_dlls)
+    };
+
+    let backup = if backup_needed {
+        let backup_dir = backup_location.dir(&managed)?;
+        Some(unique_name_for_dll(fs, &backup_dir, target_classification))
+    } else {
+        None
+    };
+
+    Ok(InstallPlan {
+        target,
+        target_classification: Some(target_classification),
+        backup,
+    })
+}
+
+#[instrument(skip(fs, game_dir, all_dlls))]
+fn prepare_target(
+    fs: &impl DllFs,
+    game_dir: &GameDir,
+    all_dlls: &[OriDll],
+    backup_location: BackupLocation,
+) -> Result<InstallPlan, InstallError> {
+    let plan = plan_install_with(fs, game_dir, all_dlls, backup_location)?;
+
+    if let Some(new_name) = &plan.backup {
+        if let Some(backup_dir) = new_name.parent() {
+            fs.create_dir_all(backup_dir)
+                .map_err(|source| InstallError::CreateBackupDir {
+                    path: backup_dir.to_owned(),
+                    source,
+                })?;
+        }
+        info!(install_target=?plan.target, ?new_name, "Renaming dll as backup");
+        fs.rename(&plan.target, new_name)
+            .map_err(|source| InstallError::Backup {
+                path: new_name.clone(),
+                source,
+            })?;
     }
 
-    Ok(target)
+    Ok(plan)
+}
+
+/// Renames `from` to `to`, falling back to copy and delete if they are on different volumes.
+#[instrument]
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            debug!(?err, "Can't rename across volumes, copying instead");
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)
+        }
+        result => result,
+    }
 }
 
 #[instrument(skip(all_dlls), ret)]
@@ -126,8 +583,8 @@ fn should_backup_target(
     !copy_exists
 }
 
-#[instrument]
-fn unique_name_for_dll(target_dir: &Path, dll: DllClassification) -> PathBuf {
+#[instrument(skip(fs))]
+fn unique_name_for_dll(fs: &impl DllFs, target_dir: &Path, dll: DllClassification) -> PathBuf {
     let target_name = match dll {
         DllClassification::Vanilla => Cow::Borrowed("Assembly-CSharp.vanilla"),
         DllClassification::UnknownRando(_) => "Assembly-CSharp.rando".into(),
@@ -137,7 +594,7 @@ fn unique_name_for_dll(target_dir: &Path, dll: DllClassification) -> PathBuf {
 
     let target_path = target_dir.join(format!("{target_name}.dll"));
 
-    if let Ok(false) = std::fs::exists(&target_path) {
+    if let Ok(false) = fs.exists(&target_path) {
         return target_path;
     }
 
@@ -146,22 +603,226 @@ fn unique_name_for_dll(target_dir: &Path, dll: DllClassification) -> PathBuf {
     target_dir.join(format!("{target_name}.{random_suffix}.dll"))
 }
 
+/// Returns the active dll, all known versions without duplicates,
+/// and the stray Ori assemblies that don't follow the naming scheme of the installer.
 #[instrument]
-pub fn search_game_dir(game_dir: &GameDir) -> Result<(Option<OriDll>, Vec<OriDll>)> {
+pub fn search_game_dir(game_dir: &GameDir) -> Result<(Option<OriDll>, Vec<OriDll>, Vec<PathBuf>)> {
+    game_dir.check_reachable()?;
+    let _lock = lock_managed_dir();
+    let managed = game_dir.resolved_managed();
+    let mut all_dlls = scan_all_dlls(&managed)?;
+
+    let stray_dlls: Vec<_> = all_dlls
+        .iter()
+        .filter(|dll| !is_installer_dll_name(&dll.path))
+        .map(|dll| dll.path.clone())
+        .collect();
+    if !stray_dlls.is_empty() {
+        warn!(
+            ?stray_dlls,
+            "Found stray Ori assemblies, which may confuse the game"
+        );
+    }
+
+    let installed_path = managed.join("Assembly-CSharp.dll");
+    let current_idx = all_dlls.iter().position(|dll| dll.path == installed_path);
+    let current = current_idx.map(|i| all_dlls[i].clone());
+
+    sort_and_filter_duplicates(&mut all_dlls, current_idx);
+
+    Ok((current, all_dlls, stray_dlls))
+}
+
+/// Files next to the active dll that some builds ship their version in.
+const SIDECAR_VERSION_FILES: &[&str] = &["version.txt"];
+
+/// A sidecar file only belongs to the active dll if both were written at about the same time,
+/// e.g. extracted from the same release archive. Installing another dll leaves the old sidecar behind.
+const SIDECAR_MAX_TIME_DIFFERENCE: Duration = Duration::from_secs(10 * 60);
+
+/// Builds without an embedded version sometimes ship it in a sidecar file next to the active dll.
+/// If the sidecar belongs to the active dll, the active dll and its copies are known versions instead of unknown builds.
+#[instrument(skip(all_dlls))]
+fn apply_sidecar_version(managed: &Path, all_dlls: &mut [OriDll]) {
+    let installed_path = managed.join("Assembly-CSharp.dll");
+    let Some(active) = all_dlls.iter().find(|dll| dll.path == installed_path) else {
+        return;
+    };
+    let OriDllKind::UnknownRando(hash) = active.kind else {
+        return;
+    };
+    let Some(dll_modified) = active.modified else {
+        debug!("Modified time of the active dll unknown, ignoring sidecar version files");
+        return;
+    };
+
+    let version = SIDECAR_VERSION_FILES
+        .iter()
+        .find_map(|name| read_sidecar_version(&managed.join(name), dll_modified));
+    let Some(version) = version else {
+        return;
+    };
+
+    info!(?version, "Using version from sidecar file for active dll");
+    for dll in all_dlls
+        .iter_mut()
+        .filter(|dll| dll.kind == OriDllKind::UnknownRando(hash))
+    {
+        dll.kind = OriDllKind::Rando(version);
+    }
+}
+
+fn read_sidecar_version(path: &Path, dll_modified: SystemTime) -> Option<RandoVersion> {
+    let sidecar_modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let difference = sidecar_modified
+        .duration_since(dll_modified)
+        .unwrap_or_else(|err| err.duration());
+    if difference > SIDECAR_MAX_TIME_DIFFERENCE {
+        info!(
+            ?path,
+            ?difference,
+            "Sidecar version file wasn't written with the active dll, ignoring it"
+        );
+        return None;
+    }
+
+    let contents = std::fs::read_to_string(path).ok()?;
+    let version = RandoVersion::parse(&contents);
+    if version.is_none() {
+        warn!(?path, ?contents, "Couldn't parse sidecar version file");
+    }
+    version
+}
+
+/// Whether `path` is the active dll or one of the backups created by [`unique_name_for_dll`].
+/// Any other Ori assembly is likely a stray copy, e.g. "Assembly-CSharp - Copy.dll".
+fn is_installer_dll_name(path: &Path) -> bool {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|name| name.starts_with("Assembly-CSharp."))
+}
+
+/// Classifies all files in the Managed directory and the app data backups, without filtering duplicates.
+/// Copies of the active dll get the version of its sidecar file, see [`apply_sidecar_version`].
+#[instrument]
+fn scan_all_dlls(managed: &Path) -> Result<Vec<OriDll>> {
+    if !managed.is_dir() {
+        return Err(eyre!(
+            "Managed directory not found, is this really an Ori DE install?"
+        ))
+        .with_section(|| format!("{managed:?}").header("Managed directory"));
+    }
+
+    check_looks_like_managed(managed)?;
+    let mut all_dlls = scan_dir(managed)?;
+
+    match app_data_backup_dir(managed) {
+        Ok(backup_dir) if backup_dir.is_dir() => {
+            all_dlls.extend(scan_dir(&backup_dir).wrap_err("Couldn't scan app data backups")?);
+        }
+        Ok(_) => {}
+        Err(err) => warn!(?err, "Couldn't locate app data backups"),
+    }
+
+    apply_sidecar_version(managed, &mut all_dlls);
+
+    Ok(all_dlls)
+}
+
+/// A real Managed directory has a few dozen files, the game assembly among them.
+const MANAGED_ENTRY_LIMIT: usize = 1000;
+
+/// The directory isn't an Ori Managed directory, it has lots of files but no game assembly.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{path:?} doesn't look like an Ori install")]
+pub struct NotAnOriInstall {
+    pub path: PathBuf,
+}
+
+/// Classifying every file of a wrong directory, e.g. a system folder, would take ages.
+/// Listing file names is cheap though, so huge directories have to contain the game assembly early on.
+/// Small directories are always scanned.
+#[instrument]
+fn check_looks_like_managed(managed: &Path) -> Result<()> {
+    let entries = read_dir(managed).wrap_err("Couldn't read ori dll dir")?;
+    for (index, entry) in entries.enumerate() {
+        if index >= MANAGED_ENTRY_LIMIT {
+            warn!("No game assembly among the first entries, not scanning the directory");
+            return Err(NotAnOriInstall {
+                path: managed.to_owned(),
+            }
+            .into());
+        }
+
+        if entry.is_ok_and(|entry| is_game_assembly_name(&entry.file_name())) {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Whether a file named `name` might be the game assembly or a copy of it, e.g. "Assembly-CSharp - Copy.dll".
+/// The installer names all backups and exports like that, so other files never need to be opened.
+fn is_game_assembly_name(name: &OsStr) -> bool {
+    name.to_string_lossy()
+        .to_lowercase()
+        .starts_with("assembly-csharp")
+}
+
+/// Every file being classified is mapped into memory,
+/// so the Managed directory with its dozens of assemblies is only scanned a few files at a time.
+const SCAN_THREADS: usize = 4;
+
+static SCAN_POOL: LazyLock<Option<ThreadPool>> = LazyLock::new(|| {
+    ThreadPoolBuilder::new()
+        .num_threads(SCAN_THREADS)
+        .thread_name(|index| format!("dll-scan-{index}"))
+        .build()
+        .inspect_err(|err| warn!(?err, "Couldn't create dll scan pool, using the global pool"))
+        .ok()
+});
+
+/// Classifies all files in `dir` that are named like the game assembly.
+#[instrument]
+fn scan_dir(dir: &Path) -> Result<Vec<OriDll>> {
+    let started = Instant::now();
+    // The pool threads don't inherit the span
     let current_span = Span::current();
+    let entries = read_dir(dir).wrap_err("Couldn't read ori dll dir")?;
+    let dlls = match &*SCAN_POOL {
+        Some(pool) => pool.install(|| classify_entries(entries, &current_span)),
+        None => classify_entries(entries, &current_span),
+    };
+    debug!(found = dlls.len(), elapsed = ?started.elapsed(), "Scanned dir");
+    Ok(dlls)
+}
 
-    let mut all_dlls = read_dir(&game_dir.managed)
-        .wrap_err("Couldn't read ori dll dir")?
+/// Entries that can't be read or classified are skipped,
+/// a single locked file shouldn't keep all the other versions from being found.
+fn classify_entries(entries: ReadDir, current_span: &Span) -> Vec<OriDll> {
+    entries
         .par_bridge()
         .filter_map(|file| {
             let _span = current_span.enter();
 
             let file = match file {
                 Ok(f) => f,
-                Err(err) => return Some(Err(err).wrap_err("Couldn't list dll file")),
+                Err(err) => {
+                    error!(?err, "Couldn't list dll file");
+                    return None;
+                }
             };
 
+            // Opening a file is slow, e.g. with a virus scanner checking it, so the name is checked first
+            if !is_game_assembly_name(&file.file_name()) {
+                return None;
+            }
+
             let path = file.path();
+            if file.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                debug!(?path, "Skipping directory");
+                return None;
+            }
 
             let classification = match classify_dll_file(&path) {
                 Ok(c) => c,
@@ -173,17 +834,146 @@ pub fn search_game_dir(game_dir: &GameDir) -> Result<(Option<OriDll>, Vec<OriDll
 
             debug!(?path, ?classification, "Classified file");
 
-            OriDll::new(path, classification).map(Ok)
+            let modified = file.metadata().and_then(|m| m.modified()).ok();
+
+            OriDll::new(path, classification, modified)
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect()
+}
 
-    let installed_path = game_dir.managed.join("Assembly-CSharp.dll");
-    let current_idx = all_dlls.iter().position(|dll| dll.path == installed_path);
-    let current = current_idx.map(|i| all_dlls[i].clone());
+/// What uninstalling the randomizer would do.
+#[derive(Debug, Clone)]
+pub struct UninstallPlan {
+    /// The vanilla dll to restore.
+    pub vanilla: OriDll,
+    /// All randomizer dlls, including duplicates, which would be deleted when cleaning up backups.
+    /// Only files named by the installer are part of this, never stray copies the user made.
+    pub rando_files: Vec<PathBuf>,
+    all_dlls: Vec<OriDll>,
+}
+
+/// Computes what [`uninstall`] would do, without touching any files.
+/// Fails if there is no vanilla dll to restore.
+#[instrument]
+pub fn plan_uninstall(game_dir: &GameDir) -> Result<UninstallPlan> {
+    game_dir.check_reachable()?;
+    let _lock = lock_managed_dir();
+    let managed = game_dir.resolved_managed();
+    let mut all_dlls = scan_all_dlls(&managed)?;
+
+    let vanilla = all_dlls
+        .iter()
+        .find(|dll| dll.kind == OriDllKind::Vanilla)
+        .cloned()
+        .ok_or_eyre("No vanilla backup found")?;
 
+    let rando_files = all_dlls
+        .iter()
+        .filter(|dll| matches!(dll.kind, OriDllKind::Rando(_) | OriDllKind::UnknownRando(_)))
+        .filter(|dll| is_installer_dll_name(&dll.path))
+        .map(|dll| dll.path.clone())
+        .collect();
+
+    let installed_path = managed.join("Assembly-CSharp.dll");
+    let current_idx = all_dlls.iter().position(|dll| dll.path == installed_path);
     sort_and_filter_duplicates(&mut all_dlls, current_idx);
 
-    Ok((current, all_dlls))
+    Ok(UninstallPlan {
+        vanilla,
+        rando_files,
+        all_dlls,
+    })
+}
+
+/// Restores vanilla and, if `delete_backups` is set, deletes all randomizer dlls of the plan.
+#[instrument(skip(plan))]
+pub fn uninstall(
+    game_dir: &GameDir,
+    plan: &UninstallPlan,
+    delete_backups: bool,
+    backup_location: BackupLocation,
+) -> Result<()> {
+    game_dir.check_reachable()?;
+    let _lock = lock_managed_dir();
+    uninstall_with(&RealFs, game_dir, plan, delete_backups, backup_location)
+}
+
+fn uninstall_with(
+    fs: &impl DllFs,
+    game_dir: &GameDir,
+    plan: &UninstallPlan,
+    delete_backups: bool,
+    backup_location: BackupLocation,
+) -> Result<()> {
+    if !delete_backups {
+        install_dll_with(fs, game_dir, &plan.vanilla, &plan.all_dlls, backup_location)?;
+        return Ok(());
+    }
+
+    // The active dll is about to be deleted anyway, so there's no need to back it up
+    let target = game_dir.resolved_managed().join("Assembly-CSharp.dll");
+    if plan.vanilla.path != target {
+        info!(?target, "Restoring vanilla");
+        fs.copy(&plan.vanilla.path, &target)
+            .wrap_err("Error restoring vanilla")?;
+    }
+
+    for path in plan.rando_files.iter().filter(|&path| *path != target) {
+        info!(?path, "Deleting rando dll");
+        fs.remove_file(path)
+            .wrap_err("Error deleting rando dll")
+            .with_section(|| format!("{path:?}").header("File path"))?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the oldest rando backups by modified date, so at most `max_backups` of them are kept.
+/// The active dll, vanilla and copies of the newest rando version are never deleted.
+/// Neither are stray copies the user made, only files named by the installer count as backups.
+#[instrument(skip(game_dir))]
+pub fn prune_backups(game_dir: &GameDir, max_backups: usize) -> Result<()> {
+    game_dir.check_reachable()?;
+    let _lock = lock_managed_dir();
+    let managed = game_dir.resolved_managed();
+    let target = managed.join("Assembly-CSharp.dll");
+    let all_dlls = scan_all_dlls(&managed)?;
+
+    let newest_version = all_dlls
+        .iter()
+        .filter(|dll| matches!(dll.kind, OriDllKind::Rando(_)))
+        .map(|dll| dll.kind)
+        .max();
+
+    let mut backups: Vec<_> = all_dlls
+        .into_iter()
+        .filter(|dll| dll.path != target && is_installer_dll_name(&dll.path))
+        .filter(|dll| matches!(dll.kind, OriDllKind::Rando(_) | OriDllKind::UnknownRando(_)))
+        .filter_map(
+            |dll| match std::fs::metadata(&dll.path).and_then(|m| m.modified()) {
+                Ok(modified) => Some((modified, dll)),
+                Err(err) => {
+                    warn!(path=?dll.path, ?err, "Couldn't read modified time, keeping backup");
+                    None
+                }
+            },
+        )
+        .collect();
+    backups.sort_by_key(|(modified, _)| Reverse(*modified));
+
+    let to_prune = backups
+        .into_iter()
+        .skip(max_backups)
+        .filter(|(_, dll)| Some(dll.kind) != newest_version);
+
+    for (_, dll) in to_prune {
+        info!(path=?dll.path, "Pruning old backup");
+        std::fs::remove_file(&dll.path)
+            .wrap_err("Error deleting backup")
+            .with_section(|| format!("{:?}", dll.path).header("File path"))?;
+    }
+
+    Ok(())
 }
 
 #[instrument(skip_all)]
@@ -216,3 +1006,455 @@ fn same_as_previous(previous: &mut Option<OriDllKind>, new: OriDllKind) -> bool
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TempDir, dotnet_dll, rando_dll, vanilla_dll};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    const VERSION: RandoVersion = RandoVersion {
+        major: 1,
+        minor: 2,
+        patch: 3,
+    };
+
+    /// Keeps the classification of every file instead of its contents, nothing touches the disk.
+    #[derive(Default)]
+    struct MemoryFs {
+        files: RefCell<HashMap<PathBuf, DllClassification>>,
+    }
+
+    impl MemoryFs {
+        fn with_files<const N: usize>(files: [(PathBuf, DllClassification); N]) -> Self {
+            Self {
+                files: RefCell::new(files.into_iter().collect()),
+            }
+        }
+    }
+
+    impl DllFs for MemoryFs {
+        fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let classification = self.classify(from)?;
+            self.files
+                .borrow_mut()
+                .insert(to.to_owned(), classification);
+            Ok(())
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.files
+                .borrow_mut()
+                .insert(path.to_owned(), classify_dll(contents));
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let classification = self
+                .files
+                .borrow_mut()
+                .remove(from)
+                .ok_or(ErrorKind::NotFound)?;
+            self.files
+                .borrow_mut()
+                .insert(to.to_owned(), classification);
+            Ok(())
+        }
+
+        fn exists(&self, path: &Path) -> io::Result<bool> {
+            Ok(self.files.borrow().contains_key(path))
+        }
+
+        fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.files
+                .borrow_mut()
+                .remove(path)
+                .ok_or(ErrorKind::NotFound)?;
+            Ok(())
+        }
+
+        fn classify(&self, path: &Path) -> io::Result<DllClassification> {
+            Ok(*self.files.borrow().get(path).ok_or(ErrorKind::NotFound)?)
+        }
+    }
+
+    fn game_dir() -> (GameDir, PathBuf) {
+        let game_dir = GameDir::new(PathBuf::from(r"C:\Games\Ori DE"));
+        let managed = game_dir.resolved_managed();
+        (game_dir, managed)
+    }
+
+    fn dll(path: &Path, classification: DllClassification) -> OriDll {
+        OriDll::new(path.to_owned(), classification, None).unwrap()
+    }
+
+    #[test]
+    fn plans_nothing_without_target() {
+        let (game_dir, managed) = game_dir();
+        let fs = MemoryFs::default();
+
+        let plan = plan_install_with(&fs, &game_dir, &[], BackupLocation::Managed).unwrap();
+
+        assert_eq!(plan.target, managed.join("Assembly-CSharp.dll"));
+        assert_eq!(plan.target_classification, None);
+        assert_eq!(plan.backup, None);
+    }
+
+    #[test]
+    fn backs_up_vanilla_without_backup() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let fs = MemoryFs::with_files([(target.clone(), DllClassification::Vanilla)]);
+        let all_dlls = [dll(&target, DllClassification::Vanilla)];
+
+        let plan = plan_install_with(&fs, &game_dir, &all_dlls, BackupLocation::Managed).unwrap();
+
+        assert_eq!(plan.target_classification, Some(DllClassification::Vanilla));
+        assert_eq!(
+            plan.backup,
+            Some(managed.join("Assembly-CSharp.vanilla.dll"))
+        );
+        assert_eq!(plan.vanilla_backup(), plan.backup.as_deref());
+    }
+
+    #[test]
+    fn keeps_vanilla_with_existing_backup() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let backup = managed.join("Assembly-CSharp.vanilla.dll");
+        let fs = MemoryFs::with_files([
+            (target.clone(), DllClassification::Vanilla),
+            (backup.clone(), DllClassification::Vanilla),
+        ]);
+        let all_dlls = [
+            dll(&target, DllClassification::Vanilla),
+            dll(&backup, DllClassification::Vanilla),
+        ];
+
+        let plan = plan_install_with(&fs, &game_dir, &all_dlls, BackupLocation::Managed).unwrap();
+
+        assert_eq!(plan.target_classification, Some(DllClassification::Vanilla));
+        assert_eq!(plan.backup, None);
+    }
+
+    #[test]
+    fn backs_up_vanilla_if_listed_backup_is_gone() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let fs = MemoryFs::with_files([(target.clone(), DllClassification::Vanilla)]);
+        let all_dlls = [
+            dll(&target, DllClassification::Vanilla),
+            dll(
+                &managed.join("Assembly-CSharp.vanilla.dll"),
+                DllClassification::Vanilla,
+            ),
+        ];
+
+        let plan = plan_install_with(&fs, &game_dir, &all_dlls, BackupLocation::Managed).unwrap();
+
+        assert_eq!(
+            plan.backup,
+            Some(managed.join("Assembly-CSharp.vanilla.dll"))
+        );
+    }
+
+    #[test]
+    fn backs_up_rando_target_without_copy() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let rando = DllClassification::Rando(VERSION);
+        let fs = MemoryFs::with_files([(target.clone(), rando)]);
+        let all_dlls = [dll(&target, rando)];
+
+        let plan = plan_install_with(&fs, &game_dir, &all_dlls, BackupLocation::Managed).unwrap();
+
+        assert_eq!(plan.target_classification, Some(rando));
+        assert_eq!(
+            plan.backup,
+            Some(managed.join("Assembly-CSharp.rando.1.2.3.dll"))
+        );
+        assert_eq!(plan.vanilla_backup(), None);
+    }
+
+    #[test]
+    fn keeps_rando_target_with_copy() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let copy = managed.join("Assembly-CSharp.rando.1.2.3.dll");
+        let rando = DllClassification::Rando(VERSION);
+        let fs = MemoryFs::with_files([(target.clone(), rando), (copy.clone(), rando)]);
+        let all_dlls = [dll(&target, rando), dll(&copy, rando)];
+
+        let plan = plan_install_with(&fs, &game_dir, &all_dlls, BackupLocation::Managed).unwrap();
+
+        assert_eq!(plan.backup, None);
+    }
+
+    #[test]
+    fn backup_needed_only_without_matching_copy() {
+        let target = Path::new(r"C:\Managed\Assembly-CSharp.dll");
+        let other = Path::new(r"C:\Managed\Assembly-CSharp.rando.dll");
+        let rando = DllClassification::Rando(VERSION);
+        let other_version = DllClassification::Rando(RandoVersion {
+            patch: 4,
+            ..VERSION
+        });
+
+        assert!(should_backup_target(target, rando, &[]));
+        assert!(should_backup_target(target, rando, &[dll(target, rando)]));
+        assert!(should_backup_target(
+            target,
+            rando,
+            &[dll(other, other_version)]
+        ));
+        assert!(!should_backup_target(target, rando, &[dll(other, rando)]));
+
+        let unknown = DllClassification::UnknownRando(1);
+        assert!(should_backup_target(
+            target,
+            unknown,
+            &[dll(other, DllClassification::UnknownRando(2))]
+        ));
+        assert!(!should_backup_target(
+            target,
+            unknown,
+            &[dll(other, unknown)]
+        ));
+    }
+
+    #[test]
+    fn unique_name_without_collision() {
+        let dir = Path::new(r"C:\Managed");
+        let fs = MemoryFs::default();
+
+        assert_eq!(
+            unique_name_for_dll(&fs, dir, DllClassification::Vanilla),
+            dir.join("Assembly-CSharp.vanilla.dll")
+        );
+        assert_eq!(
+            unique_name_for_dll(&fs, dir, DllClassification::Rando(VERSION)),
+            dir.join("Assembly-CSharp.rando.1.2.3.dll")
+        );
+        assert_eq!(
+            unique_name_for_dll(&fs, dir, DllClassification::UnknownRando(1)),
+            dir.join("Assembly-CSharp.rando.dll")
+        );
+    }
+
+    #[test]
+    fn unique_name_with_collision_gets_suffix() {
+        let dir = Path::new(r"C:\Managed");
+        let taken = dir.join("Assembly-CSharp.vanilla.dll");
+        let fs = MemoryFs::with_files([(taken.clone(), DllClassification::Vanilla)]);
+
+        let name = unique_name_for_dll(&fs, dir, DllClassification::Vanilla);
+
+        assert_ne!(name, taken);
+        assert_eq!(name.parent(), Some(dir));
+        let file_name = name.file_name().unwrap().to_str().unwrap();
+        let suffix = file_name
+            .strip_prefix("Assembly-CSharp.vanilla.")
+            .and_then(|rest| rest.strip_suffix(".dll"))
+            .unwrap();
+        assert_eq!(suffix.len(), 10);
+        assert!(suffix.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn install_moves_target_to_planned_backup() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let source = managed.join("Assembly-CSharp.rando.1.2.3.dll");
+        let rando = DllClassification::Rando(VERSION);
+        let fs = MemoryFs::with_files([
+            (target.clone(), DllClassification::Vanilla),
+            (source.clone(), rando),
+        ]);
+        let all_dlls = [
+            dll(&target, DllClassification::Vanilla),
+            dll(&source, rando),
+        ];
+
+        let plan = install_dll_with(
+            &fs,
+            &game_dir,
+            &all_dlls[1],
+            &all_dlls,
+            BackupLocation::Managed,
+        )
+        .unwrap();
+
+        let backup = plan.backup.unwrap();
+        assert_eq!(fs.classify(&backup).unwrap(), DllClassification::Vanilla);
+        assert_eq!(fs.classify(&target).unwrap(), rando);
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn install_keeps_modified_time_of_source() {
+        let dir = TempDir::new("install-modified-time");
+        let game_dir = GameDir::new(dir.path().to_owned());
+        std::fs::create_dir_all(&game_dir.managed).unwrap();
+        let managed = game_dir.resolved_managed();
+        let target = managed.join("Assembly-CSharp.dll");
+        let source = managed.join("Assembly-CSharp.rando.1.2.3.dll");
+        std::fs::write(&target, vanilla_dll()).unwrap();
+        std::fs::write(&source, rando_dll("1.2.3")).unwrap();
+        let released = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        set_modified(&source, released);
+        let all_dlls = [
+            dll(&target, DllClassification::Vanilla),
+            dll(&source, DllClassification::Rando(VERSION)),
+        ];
+
+        let plan = install_dll_with(
+            &RealFs,
+            &game_dir,
+            &all_dlls[1],
+            &all_dlls,
+            BackupLocation::Managed,
+        )
+        .unwrap();
+
+        let modified = |path: &Path| std::fs::metadata(path).unwrap().modified().unwrap();
+        assert_eq!(modified(&target), released);
+        assert_eq!(modified(&source), released);
+        assert_eq!(
+            classify_dll_file(&plan.backup.unwrap()).unwrap(),
+            DllClassification::Vanilla
+        );
+    }
+
+    /// Writes a randomizer build without an embedded version as the active dll, with a backup of it,
+    /// and a sidecar version file written `sidecar_age` before the build.
+    fn write_build_with_sidecar(managed: &Path, sidecar_age: Duration) {
+        let build = dotnet_dll(b"\0SpiritGrenadeDamageDealer\0Randomizer\0", None, 0);
+        let built = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+        for name in ["Assembly-CSharp.dll", "Assembly-CSharp.rando.dll"] {
+            let path = managed.join(name);
+            std::fs::write(&path, &build).unwrap();
+            set_modified(&path, built);
+        }
+        let sidecar = managed.join("version.txt");
+        std::fs::write(&sidecar, "v1.2.3\n").unwrap();
+        set_modified(&sidecar, built - sidecar_age);
+    }
+
+    #[test]
+    fn sidecar_version_applies_to_active_dll_and_copies() {
+        let dir = TempDir::new("sidecar-version");
+        write_build_with_sidecar(dir.path(), Duration::from_secs(5));
+
+        let all_dlls = scan_all_dlls(dir.path()).unwrap();
+
+        assert_eq!(all_dlls.len(), 2);
+        assert!(
+            all_dlls
+                .iter()
+                .all(|dll| dll.kind == OriDllKind::Rando(VERSION))
+        );
+    }
+
+    #[test]
+    fn ignores_sidecar_of_other_build() {
+        let dir = TempDir::new("sidecar-version-stale");
+        // Left behind by a build installed the day before, the current build doesn't ship one
+        write_build_with_sidecar(dir.path(), Duration::from_secs(24 * 60 * 60));
+
+        let all_dlls = scan_all_dlls(dir.path()).unwrap();
+
+        assert_eq!(all_dlls.len(), 2);
+        assert!(
+            all_dlls
+                .iter()
+                .all(|dll| matches!(dll.kind, OriDllKind::UnknownRando(_)))
+        );
+    }
+
+    #[test]
+    fn prune_keeps_stray_copies() {
+        let dir = TempDir::new("prune-stray");
+        let game_dir = GameDir::new(dir.path().to_owned());
+        std::fs::create_dir_all(&game_dir.managed).unwrap();
+        let managed = game_dir.resolved_managed();
+        let now = SystemTime::now();
+        let files = [
+            ("Assembly-CSharp.dll", "4.1.0", 0),
+            ("Assembly-CSharp.rando.4.0.0.dll", "4.0.0", 2),
+            ("Assembly-CSharp - Copy.dll", "3.0.0", 3),
+            ("Assembly-CSharp.rando.4.1.0.dll", "4.1.0", 1),
+        ];
+        for (name, version, days_old) in files {
+            let path = managed.join(name);
+            std::fs::write(&path, rando_dll(version)).unwrap();
+            set_modified(&path, now - Duration::from_secs(days_old * 24 * 60 * 60));
+        }
+
+        prune_backups(&game_dir, 0).unwrap();
+
+        let exists = |name| managed.join(name).exists();
+        assert!(exists("Assembly-CSharp.dll"));
+        assert!(exists("Assembly-CSharp.rando.4.1.0.dll"));
+        assert!(exists("Assembly-CSharp - Copy.dll"));
+        assert!(!exists("Assembly-CSharp.rando.4.0.0.dll"));
+    }
+
+    #[test]
+    fn uninstall_plan_skips_stray_copies() {
+        let dir = TempDir::new("uninstall-stray");
+        let game_dir = GameDir::new(dir.path().to_owned());
+        std::fs::create_dir_all(&game_dir.managed).unwrap();
+        let managed = game_dir.resolved_managed();
+        std::fs::write(managed.join("Assembly-CSharp.dll"), rando_dll("4.1.0")).unwrap();
+        std::fs::write(managed.join("Assembly-CSharp.vanilla.dll"), vanilla_dll()).unwrap();
+        std::fs::write(
+            managed.join("Assembly-CSharp - Copy.dll"),
+            rando_dll("3.0.0"),
+        )
+        .unwrap();
+
+        let plan = plan_uninstall(&game_dir).unwrap();
+
+        assert_eq!(plan.rando_files, [managed.join("Assembly-CSharp.dll")]);
+    }
+
+    #[test]
+    fn uninstall_deletes_rando_dlls_without_backup() {
+        let (game_dir, managed) = game_dir();
+        let target = managed.join("Assembly-CSharp.dll");
+        let vanilla = managed.join("Assembly-CSharp.vanilla.dll");
+        let backup = managed.join("Assembly-CSharp.rando.1.2.3.dll");
+        let fs = MemoryFs::with_files([
+            (target.clone(), DllClassification::Rando(VERSION)),
+            (vanilla.clone(), DllClassification::Vanilla),
+            (backup.clone(), DllClassification::Rando(VERSION)),
+        ]);
+        let plan = UninstallPlan {
+            vanilla: dll(&vanilla, DllClassification::Vanilla),
+            rando_files: vec![target.clone(), backup.clone()],
+            all_dlls: vec![
+                dll(&target, DllClassification::Rando(VERSION)),
+                dll(&vanilla, DllClassification::Vanilla),
+            ],
+        };
+
+        uninstall_with(&fs, &game_dir, &plan, true, BackupLocation::Managed).unwrap();
+
+        assert_eq!(fs.classify(&target).unwrap(), DllClassification::Vanilla);
+        assert_eq!(fs.classify(&vanilla).unwrap(), DllClassification::Vanilla);
+        assert!(!fs.exists(&backup).unwrap());
+    }
+}
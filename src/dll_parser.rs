@@ -2,24 +2,46 @@ use std::ops::Range;
 
 pub struct DllHeaps<'a> {
     pub strings: &'a [u8],
-    pub us: &'a [u8],
+    /// Trimmed or obfuscated assemblies may omit the user string heap.
+    pub us: Option<&'a [u8]>,
 }
 
-pub fn parse_dll(data: &[u8]) -> Result<DllHeaps<'_>, &'static str> {
-    let lfanew = read_u32(data, 60, "EOF lfanew")? as usize;
+/// Why parsing a dll failed, and where.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("{reason} at offset {offset:#x}")]
+pub struct ParseError {
+    pub reason: &'static str,
+    /// Offset into the file of the data that couldn't be parsed.
+    pub offset: usize,
+}
 
-    let pe_header = data.get(lfanew..).ok_or("Invalid lfanew")?;
+impl ParseError {
+    /// `offset` is relative to `part`, which has to be a subslice of `file`.
+    fn at(file: &[u8], part: &[u8], offset: usize, reason: &'static str) -> Self {
+        Self {
+            reason,
+            offset: part.as_ptr().addr() - file.as_ptr().addr() + offset,
+        }
+    }
+}
+
+pub fn parse_dll(data: &[u8]) -> Result<DllHeaps<'_>, ParseError> {
+    let lfanew = read_u32(data, data, 60, "EOF lfanew")? as usize;
+
+    let pe_header =
+        data.get(lfanew..)
+            .ok_or(ParseError::at(data, data, lfanew, "Invalid lfanew"))?;
 
     if pe_header.get(0..4) != Some(b"PE\0\0") {
-        return Err("Invalid PE magic");
+        return Err(ParseError::at(data, pe_header, 0, "Invalid PE magic"));
     }
 
-    let num_sections = read_u16(pe_header, 6, "EOF num_sections")? as usize;
-    let opt_header_size = read_u16(pe_header, 20, "EOF opt_header_size")? as usize;
+    let num_sections = read_u16(data, pe_header, 6, "EOF num_sections")? as usize;
+    let opt_header_size = read_u16(data, pe_header, 20, "EOF opt_header_size")? as usize;
 
     let optional_header = pe_header
         .get(24..24 + opt_header_size)
-        .ok_or("EOF optional_header")?;
+        .ok_or(ParseError::at(data, pe_header, 24, "EOF optional_header"))?;
 
     let sections: &[_] = &{
         let mut sections = Vec::with_capacity(num_sections);
@@ -27,14 +49,14 @@ pub fn parse_dll(data: &[u8]) -> Result<DllHeaps<'_>, &'static str> {
         for i in 0..num_sections {
             let section_start = 24 + opt_header_size + i * 40;
 
-            let virtual_size = read_u32(pe_header, section_start + 8, "EOF section")?;
-            let virtual_start = read_u32(pe_header, section_start + 12, "EOF section")?;
-            let file_size = read_u32(pe_header, section_start + 16, "EOF section")? as usize;
-            let file_start = read_u32(pe_header, section_start + 20, "EOF section")? as usize;
+            let virtual_size = read_u32(data, pe_header, section_start + 8, "EOF section")?;
+            let virtual_start = read_u32(data, pe_header, section_start + 12, "EOF section")?;
+            let file_size = read_u32(data, pe_header, section_start + 16, "EOF section")? as usize;
+            let file_start = read_u32(data, pe_header, section_start + 20, "EOF section")? as usize;
 
             let file_bytes = data
                 .get(file_start..file_start + file_size)
-                .ok_or("EOF section data")?;
+                .ok_or(ParseError::at(data, data, file_start, "EOF section data"))?;
 
             sections.push(DllSection {
                 virtual_range: virtual_start..virtual_start + virtual_size,
@@ -45,48 +67,72 @@ pub fn parse_dll(data: &[u8]) -> Result<DllHeaps<'_>, &'static str> {
         sections
     };
 
-    let cli_header_rva = read_u32(optional_header, 208, "opt_header too small")?;
-    let cli_header = resolve_rva(cli_header_rva, sections, "Invalid CLI header RVA")?;
-
-    let metadata_rva = read_u32(cli_header, 8, "EOF metadata rva")?;
-    let metadata = resolve_rva(metadata_rva, sections, "Invalid metadata RVA")?;
+    let cli_header_rva = read_u32(data, optional_header, 208, "opt_header too small")?;
+    let cli_header = resolve_rva(cli_header_rva, sections).ok_or(ParseError::at(
+        data,
+        optional_header,
+        208,
+        "Invalid CLI header RVA",
+    ))?;
+
+    let metadata_rva = read_u32(data, cli_header, 8, "EOF metadata rva")?;
+    let metadata = resolve_rva(metadata_rva, sections).ok_or(ParseError::at(
+        data,
+        cli_header,
+        8,
+        "Invalid metadata RVA",
+    ))?;
 
     if metadata.get(0..4) != Some(b"BSJB") {
-        return Err("Invalid metadata magic");
+        return Err(ParseError::at(data, metadata, 0, "Invalid metadata magic"));
     }
 
     let version_length =
-        read_u32(metadata, 12, "EOF metadata version length")?.next_multiple_of(4) as usize;
-    let num_streams = read_u16(metadata, 16 + version_length + 2, "EOF num_streams")? as usize;
+        read_u32(data, metadata, 12, "EOF metadata version length")?.next_multiple_of(4) as usize;
+    let num_streams =
+        read_u16(data, metadata, 16 + version_length + 2, "EOF num_streams")? as usize;
 
     let streams: &[_] = &{
         let mut stream_header = metadata
             .get(16 + version_length + 4..)
-            .ok_or("EOF streams")?;
+            .ok_or(ParseError::at(
+                data,
+                metadata,
+                16 + version_length + 4,
+                "EOF streams",
+            ))?;
 
         let mut streams = Vec::with_capacity(num_streams);
 
         for _ in 0..num_streams {
-            let offset = read_u32(stream_header, 0, "EOF stream offset")? as usize;
-            let size = read_u32(stream_header, 4, "EOF stream size")? as usize;
+            let offset = read_u32(data, stream_header, 0, "EOF stream offset")? as usize;
+            let size = read_u32(data, stream_header, 4, "EOF stream size")? as usize;
 
-            let name_length = stream_header[8..]
-                .iter()
-                .position(|&c| c == 0)
-                .ok_or("EOF stream name")?;
+            let name_length = stream_header
+                .get(8..)
+                .and_then(|name| name.iter().position(|&c| c == 0))
+                .ok_or(ParseError::at(data, stream_header, 8, "EOF stream name"))?;
 
             streams.push(CliStream {
                 name: &stream_header[8..8 + name_length],
-                data: metadata
-                    .get(offset..offset + size)
-                    .ok_or("EOF stream data")?,
+                data: metadata.get(offset..offset + size).ok_or(ParseError::at(
+                    data,
+                    metadata,
+                    offset,
+                    "EOF stream data",
+                ))?,
             });
 
             let rounded_name_length = (name_length + 1).next_multiple_of(4);
 
             stream_header = stream_header
                 .get(8 + rounded_name_length..)
-                .ok_or("EOF next stream")?;
+                .ok_or(ParseError::at(
+                    data,
+                    stream_header,
+                    8 + rounded_name_length,
+                    "EOF next stream",
+                ))?;
         }
 
         streams
@@ -95,29 +141,38 @@ pub fn parse_dll(data: &[u8]) -> Result<DllHeaps<'_>, &'static str> {
     let strings_heap = streams
         .iter()
         .find(|&s| s.name == b"#Strings")
-        .ok_or("No #Strings heap")?;
+        .ok_or(ParseError::at(data, metadata, 0, "No #Strings heap"))?;
 
-    let us_heap = streams
-        .iter()
-        .find(|&s| s.name == b"#US")
-        .ok_or("No #US heap")?;
+    let us_heap = streams.iter().find(|&s| s.name == b"#US");
 
     Ok(DllHeaps {
         strings: strings_heap.data,
-        us: us_heap.data,
+        us: us_heap.map(|s| s.data),
     })
 }
 
-fn read_u16(data: &[u8], offset: usize, error_msg: &'static str) -> Result<u16, &'static str> {
+/// `file` is only used to report the offset of errors.
+fn read_u16(
+    file: &[u8],
+    data: &[u8],
+    offset: usize,
+    reason: &'static str,
+) -> Result<u16, ParseError> {
     match data.get(offset..offset + 2) {
-        None => Err(error_msg),
+        None => Err(ParseError::at(file, data, offset, reason)),
         Some(bytes) => Ok(u16::from_le_bytes(bytes.try_into().unwrap())),
     }
 }
 
-fn read_u32(data: &[u8], offset: usize, error_msg: &'static str) -> Result<u32, &'static str> {
+/// `file` is only used to report the offset of errors.
+fn read_u32(
+    file: &[u8],
+    data: &[u8],
+    offset: usize,
+    reason: &'static str,
+) -> Result<u32, ParseError> {
     match data.get(offset..offset + 4) {
-        None => Err(error_msg),
+        None => Err(ParseError::at(file, data, offset, reason)),
         Some(bytes) => Ok(u32::from_le_bytes(bytes.try_into().unwrap())),
     }
 }
@@ -127,25 +182,56 @@ struct DllSection<'a> {
     file_bytes: &'a [u8],
 }
 
-fn resolve_rva<'a>(
-    rva: u32,
-    sections: &[DllSection<'a>],
-    error_msg: &'static str,
-) -> Result<&'a [u8], &'static str> {
-    for section in sections {
-        if section.virtual_range.contains(&rva) {
+fn resolve_rva<'a>(rva: u32, sections: &[DllSection<'a>]) -> Option<&'a [u8]> {
+    sections
+        .iter()
+        .find(|section| section.virtual_range.contains(&rva))
+        .and_then(|section| {
             let section_offset = rva - section.virtual_range.start;
-            return section
-                .file_bytes
-                .get(section_offset as usize..)
-                .ok_or(error_msg);
-        }
-    }
-
-    Err(error_msg)
+            section.file_bytes.get(section_offset as usize..)
+        })
 }
 
 struct CliStream<'a> {
     name: &'a [u8],
     data: &'a [u8],
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dll_classifier::ByteOrder;
+    use crate::test_support::{dotnet_dll, us_heap};
+
+    const STRINGS: &[u8] = b"\0<Module>\0Assembly-CSharp\0";
+
+    #[test]
+    fn parses_heaps() {
+        let us = us_heap(&["Randomizer"], ByteOrder::Little);
+        let dll = dotnet_dll(STRINGS, Some(&us), 0);
+
+        let heaps = parse_dll(&dll).unwrap();
+
+        assert_eq!(heaps.strings, STRINGS);
+        assert_eq!(heaps.us, Some(us.as_slice()));
+    }
+
+    #[test]
+    fn parses_dll_without_us_heap() {
+        let dll = dotnet_dll(STRINGS, None, 0);
+
+        let heaps = parse_dll(&dll).unwrap();
+
+        assert_eq!(heaps.strings, STRINGS);
+        assert_eq!(heaps.us, None);
+    }
+
+    #[test]
+    fn rejects_truncated_dll() {
+        let dll = dotnet_dll(STRINGS, None, 0);
+        let reason = |data| parse_dll(data).err().map(|err| err.reason);
+
+        assert_eq!(reason(&[]), Some("EOF lfanew"));
+        assert_eq!(reason(&dll[..dll.len() - 1]), Some("EOF section data"));
+    }
+}
@@ -1,14 +1,39 @@
+use crate::dll_classifier::{DllClassification, classify_dll_file};
+use crate::paths::without_verbatim_prefix;
 use crate::settings::LaunchType;
-use crate::steam::{get_game_dir, launch_game};
-use color_eyre::Result;
+use crate::steam::{get_game_dirs, is_steam_installed, launch_game, validate_game};
 use color_eyre::eyre::{Context, bail};
+use color_eyre::{Result, Section, SectionExt};
 use serde::{Deserialize, Serialize};
 use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::io;
 use std::os::windows::ffi::{OsStrExt, OsStringExt};
-use std::path::{Path, PathBuf};
-use tracing::{debug, error, info, instrument};
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
 
 const ORI_DE_APP_ID: &str = "387290";
+/// How long a network game dir may take to respond before it's considered unavailable.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Environment variables pointing at synced folders, with the name of their sync client.
+const CLOUD_SYNC_ENV_VARS: &[(&str, &str)] = &[
+    ("OneDrive", "OneDrive"),
+    ("OneDriveConsumer", "OneDrive"),
+    ("OneDriveCommercial", "OneDrive"),
+];
+
+/// Lowercase prefixes of default sync folder names, with the name of their sync client.
+const CLOUD_SYNC_FOLDERS: &[(&str, &str)] = &[
+    ("onedrive", "OneDrive"),
+    ("dropbox", "Dropbox"),
+    ("google drive", "Google Drive"),
+    ("googledrive", "Google Drive"),
+    ("icloud", "iCloud Drive"),
+];
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(from = "GameDirS", into = "GameDirS")]
@@ -31,6 +56,94 @@ impl GameDir {
         !self.install.as_os_str().is_empty()
     }
 
+    /// The Managed directory with symlinks and junctions resolved.
+    /// Falls back to the unresolved path if it can't be canonicalized.
+    ///
+    /// Either way it's a plain path that can be shown to the user.
+    /// Raw Windows API calls need [`extended_length_path`](crate::paths::extended_length_path) for paths below it.
+    #[instrument(skip(self), fields(?self.managed))]
+    pub fn resolved_managed(&self) -> PathBuf {
+        match std::fs::canonicalize(&self.managed) {
+            Ok(path) => without_verbatim_prefix(&path),
+            Err(err) => {
+                debug!(?err, "Couldn't canonicalize managed dir, using it as is");
+                self.managed.clone()
+            }
+        }
+    }
+
+    /// Whether the game dir is on a network share, e.g. `\\server\share\Ori`.
+    pub fn is_network_path(&self) -> bool {
+        matches!(
+            self.install.components().next(),
+            Some(Component::Prefix(prefix))
+                if matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+        )
+    }
+
+    /// Accessing an unreachable network share can hang for a long time,
+    /// so network game dirs are checked with a timeout before any files get touched.
+    /// Local game dirs are always considered reachable.
+    #[instrument(skip(self), fields(?self.install))]
+    pub fn check_reachable(&self) -> Result<(), NetworkLocationUnavailable> {
+        if !self.is_network_path() {
+            return Ok(());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let install = self.install.clone();
+        thread::spawn(move || {
+            // The receiver is gone if the check timed out, nobody is interested in the result anymore
+            let _ = sender.send(std::fs::metadata(install));
+        });
+
+        match receiver.recv_timeout(NETWORK_TIMEOUT) {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(err)) => Err(NetworkLocationUnavailable {
+                path: self.install.clone(),
+                source: Some(err),
+            }),
+            Err(_) => {
+                warn!("Network game dir didn't respond in time");
+                Err(NetworkLocationUnavailable {
+                    path: self.install.clone(),
+                    source: None,
+                })
+            }
+        }
+    }
+
+    /// Whether launching with `launch_type` can work at all.
+    #[instrument(skip(self), fields(?self.install))]
+    pub fn can_launch(&self, launch_type: LaunchType) -> bool {
+        match launch_type {
+            LaunchType::Steam => is_steam_installed(),
+            LaunchType::File => self.install.join("oriDE.exe").is_file(),
+        }
+    }
+
+    /// Name of the cloud sync client the Managed directory is synced by, if any.
+    /// Sync clients lock and restore files, which can interfere with swapping the dll.
+    pub fn cloud_sync_provider(&self) -> Option<&'static str> {
+        let synced_roots = CLOUD_SYNC_ENV_VARS
+            .iter()
+            .filter_map(|(var, name)| Some((PathBuf::from(std::env::var_os(var)?), *name)));
+        for (root, name) in synced_roots {
+            if !root.as_os_str().is_empty() && self.managed.starts_with(&root) {
+                return Some(name);
+            }
+        }
+
+        // Not every client sets an environment variable, but they all default to a folder named after them
+        self.managed.components().find_map(|component| {
+            let component = component.as_os_str().to_string_lossy().to_lowercase();
+            CLOUD_SYNC_FOLDERS
+                .iter()
+                .find(|(folder, _)| component.starts_with(folder))
+                .map(|(_, name)| *name)
+        })
+    }
+
     #[instrument(skip(self), fields(?self.install))]
     pub fn launch_game_exe(&self) -> Result<()> {
         opener::open(self.install.join("oriDE.exe")).wrap_err("Error opening game exe")
@@ -52,6 +165,16 @@ impl GameDir {
     }
 }
 
+/// The game dir is on a network share that didn't respond.
+#[derive(Debug, thiserror::Error)]
+#[error("The network location {path:?} is unavailable")]
+pub struct NetworkLocationUnavailable {
+    pub path: PathBuf,
+    /// `None` if the share didn't respond in time.
+    #[source]
+    pub source: Option<io::Error>,
+}
+
 impl From<GameDir> for GameDirS {
     fn from(value: GameDir) -> Self {
         let path = value.install.into_os_string();
@@ -62,8 +185,19 @@ impl From<GameDir> for GameDirS {
     }
 }
 
+/// A broken game dir in the settings file falls back to an unset one, so it gets detected again
+/// instead of failing every operation or discarding the other settings.
 impl From<GameDirS> for GameDir {
     fn from(value: GameDirS) -> Self {
+        let valid = match &value {
+            GameDirS::String(string) => !string.contains('\0'),
+            GameDirS::Wide(wide) => !wide.is_empty() && !wide.contains(&0),
+        };
+        if !valid {
+            error!(?value, "Invalid game dir in settings, ignoring it");
+            return Self::default();
+        }
+
         Self::new(match value {
             GameDirS::String(string) => PathBuf::from(string),
             GameDirS::Wide(wide) => OsString::from_wide(&wide).into(),
@@ -72,16 +206,21 @@ impl From<GameDirS> for GameDir {
 }
 
 /// Serialized form of [`GameDir`]
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 enum GameDirS {
     String(String),
     Wide(Vec<u16>),
 }
 
+/// A game dir is valid if it contains the game exe, or, for unusual layouts with the exe elsewhere,
+/// a Definitive Edition assembly in the Managed directory.
+///
+/// Doesn't probe network game dirs, an unreachable share would block for [`NETWORK_TIMEOUT`].
+/// Callers that mustn't block skip network game dirs, the others use [`GameDir::check_reachable`] first.
 #[instrument(skip(game_dir), fields(game_dir=?game_dir.install))]
 pub fn verify_game_dir(game_dir: &GameDir) -> bool {
-    if let Err(err) = inner(&game_dir.install) {
+    if let Err(err) = inner(game_dir) {
         info!(?err, ?game_dir.install, "Failed to validate ori game directory");
         return false;
     }
@@ -89,32 +228,238 @@ pub fn verify_game_dir(game_dir: &GameDir) -> bool {
     return true;
 
     #[allow(clippy::items_after_statements)]
-    fn inner(path: &Path) -> Result<()> {
-        let exe_path = path.join("oriDE.exe");
-        let metadata = std::fs::metadata(exe_path).wrap_err("Getting exe metadata")?;
-        if !metadata.is_file() {
-            bail!("Not a file");
+    fn inner(game_dir: &GameDir) -> Result<()> {
+        verify_exe(&game_dir.install).or_else(|exe_err| {
+            verify_managed_dll(&game_dir.resolved_managed())
+                .wrap_err("Neither the game exe nor the game assembly were found")
+                .with_section(|| format!("{exe_err:?}").header("Exe error"))
+        })
+    }
+}
+
+fn verify_exe(install: &Path) -> Result<()> {
+    let exe_path = install.join("oriDE.exe");
+    let metadata = std::fs::metadata(exe_path).wrap_err("Getting exe metadata")?;
+    if !metadata.is_file() {
+        bail!("Not a file");
+    }
+    Ok(())
+}
+
+fn verify_managed_dll(managed: &Path) -> Result<()> {
+    let classification = classify_dll_file(&managed.join("Assembly-CSharp.dll"))
+        .wrap_err("Classifying game assembly")?;
+    match classification {
+        DllClassification::Vanilla
+        | DllClassification::Rando(_)
+        | DllClassification::UnknownRando(_) => Ok(()),
+        DllClassification::Invalid | DllClassification::NonDe => {
+            bail!("Not a Definitive Edition assembly: {classification:?}")
         }
-        Ok(())
     }
 }
 
+/// Lets steam verify the game files, which replaces the randomizer with vanilla.
+pub fn open_steam_verification() -> Result<()> {
+    validate_game(ORI_DE_APP_ID)
+}
+
+/// The exe alone doesn't tell the original game from the Definitive Edition, the active assembly does.
+/// A missing or unreadable assembly doesn't count, the randomizer installs its own anyway.
+pub fn is_original_game(game_dir: &GameDir) -> bool {
+    let dll_path = game_dir.resolved_managed().join("Assembly-CSharp.dll");
+    matches!(classify_dll_file(&dll_path), Ok(DllClassification::NonDe))
+}
+
+fn reject_original_game(game_dir: &GameDir) -> Result<()> {
+    if is_original_game(game_dir) {
+        bail!(
+            "This is the original Ori and the Blind Forest, the randomizer requires the Definitive Edition"
+        );
+    }
+    Ok(())
+}
+
+/// Finds the game below `dir`, for when a steam library or its `common` folder was picked instead of the game itself.
+/// Only looks one level down, plus into `steamapps\common` of a library root.
 #[instrument]
-pub fn search_for_game_dir() -> Option<GameDir> {
-    match get_game_dir(ORI_DE_APP_ID) {
-        Ok(dir) => {
-            info!(?dir, "Found ori install dir");
-
-            let game_dir = GameDir::new(dir);
-            if verify_game_dir(&game_dir) {
-                debug!("Verified ori install dir");
-                return Some(game_dir);
+pub fn search_game_dir_below(dir: &Path) -> Option<GameDir> {
+    let common = dir.join("steamapps").join("common");
+    [dir, common.as_path()]
+        .into_iter()
+        .filter_map(|parent| match std::fs::read_dir(parent) {
+            Ok(entries) => Some(entries),
+            Err(err) => {
+                debug!(?parent, ?err, "Couldn't read directory");
+                None
             }
+        })
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|child| child.join("oriDE.exe").is_file())
+        .map(GameDir::new)
+        .find(|game_dir| verify_game_dir(game_dir) && !is_original_game(game_dir))
+}
+
+/// A place the game can be installed from, each with its own way of finding the game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameSource {
+    Steam,
+}
+
+impl GameSource {
+    pub const ALL: [GameSource; 1] = [GameSource::Steam];
+
+    /// Directories this source might have installed the game to.
+    fn candidate_dirs(self) -> Result<Vec<PathBuf>> {
+        match self {
+            GameSource::Steam => get_game_dirs(ORI_DE_APP_ID),
         }
+    }
+
+    /// How a game found by this source should be launched.
+    pub fn launch_type(self) -> LaunchType {
+        match self {
+            GameSource::Steam => LaunchType::Steam,
+        }
+    }
+}
+
+impl Display for GameSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameSource::Steam => write!(f, "Steam"),
+        }
+    }
+}
+
+/// Searches every source, in the order of [`GameSource::ALL`].
+#[instrument]
+pub fn search_for_game_dir() -> Option<GameDir> {
+    GameSource::ALL.into_iter().find_map(search_for_game_dir_in)
+}
+
+/// Only searches `source`, for when the game is installed from several sources and the wrong one was found.
+#[instrument]
+pub fn search_for_game_dir_in(source: GameSource) -> Option<GameDir> {
+    let dirs = match source.candidate_dirs() {
+        Ok(dirs) => dirs,
         Err(e) => {
             info!(?e, "Failed to find ori install dir");
+            return None;
+        }
+    };
+
+    // With several steam installs, the first might have a stale or broken copy of the game
+    for dir in dirs {
+        info!(?dir, "Found ori install dir");
+
+        let game_dir = GameDir::new(dir);
+        if !verify_game_dir(&game_dir) {
+            continue;
+        }
+        if let Err(err) = reject_original_game(&game_dir) {
+            warn!(?err, "Rejecting detected ori install dir");
+            continue;
         }
+
+        debug!("Verified ori install dir");
+        return Some(game_dir);
     }
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TempDir, dotnet_dll, rando_dll, vanilla_dll};
+
+    fn round_trip(game_dir: &GameDir) -> GameDir {
+        let json = serde_json::to_string(game_dir).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn round_trips_non_bmp_path() {
+        let game_dir = GameDir::new(PathBuf::from(r"C:\Spiele\🎮 Ori 𝔇𝔈"));
+
+        assert!(matches!(
+            GameDirS::from(game_dir.clone()),
+            GameDirS::String(_)
+        ));
+        assert_eq!(round_trip(&game_dir), game_dir);
+
+        // Settings of older versions might have stored it as UTF-16, with surrogate pairs
+        let wide: Vec<u16> = game_dir.install.as_os_str().encode_wide().collect();
+        assert!(wide.iter().any(|unit| (0xD800..0xDC00).contains(unit)));
+        assert_eq!(GameDir::from(GameDirS::Wide(wide)), game_dir);
+    }
+
+    #[test]
+    fn round_trips_unpaired_surrogate() {
+        // A lone high surrogate isn't valid unicode, so it's stored as UTF-16
+        let wide = [u16::from(b'C'), u16::from(b':'), u16::from(b'\\'), 0xD83C];
+        let game_dir = GameDir::new(OsString::from_wide(&wide).into());
+
+        match GameDirS::from(game_dir.clone()) {
+            GameDirS::Wide(stored) => assert_eq!(stored, wide),
+            GameDirS::String(string) => panic!("Stored as string {string:?}"),
+        }
+        assert_eq!(round_trip(&game_dir), game_dir);
+    }
+
+    #[test]
+    fn invalid_game_dirs_fall_back_to_default() {
+        for json in [r#""C:\\Ori\u0000DE""#, "[]", "[67, 58, 0, 92]"] {
+            let game_dir: GameDir = serde_json::from_str(json).unwrap();
+            assert_eq!(game_dir, GameDir::default(), "{json}");
+        }
+    }
+
+    fn write_assembly(game_dir: &GameDir, dll: &[u8]) {
+        std::fs::create_dir_all(&game_dir.managed).unwrap();
+        std::fs::write(game_dir.managed.join("Assembly-CSharp.dll"), dll).unwrap();
+    }
+
+    #[test]
+    fn accepts_dir_with_exe() {
+        let dir = TempDir::new("verify-exe");
+        let game_dir = GameDir::new(dir.path().to_owned());
+        std::fs::write(dir.path().join("oriDE.exe"), b"MZ").unwrap();
+
+        assert!(verify_game_dir(&game_dir));
+    }
+
+    #[test]
+    fn accepts_managed_only_dir() {
+        let dir = TempDir::new("verify-managed");
+        let game_dir = GameDir::new(dir.path().to_owned());
+
+        write_assembly(&game_dir, &vanilla_dll());
+        assert!(verify_game_dir(&game_dir));
+
+        write_assembly(&game_dir, &rando_dll("4.1.0"));
+        assert!(verify_game_dir(&game_dir));
+    }
+
+    #[test]
+    fn rejects_dir_without_game() {
+        let dir = TempDir::new("verify-no-game");
+        let game_dir = GameDir::new(dir.path().to_owned());
+        assert!(!verify_game_dir(&game_dir));
+
+        // A directory named like the exe, and assemblies of the original game or of something else
+        std::fs::create_dir(dir.path().join("oriDE.exe")).unwrap();
+        assert!(!verify_game_dir(&game_dir));
+        write_assembly(
+            &game_dir,
+            &dotnet_dll(b"\0HoldingNightberryCondition\0", None, 0),
+        );
+        assert!(!verify_game_dir(&game_dir));
+        write_assembly(&game_dir, b"not a dll");
+        assert!(!verify_game_dir(&game_dir));
+
+        assert!(!verify_game_dir(&GameDir::new(dir.path().join("missing"))));
+    }
+}
@@ -1,73 +1,123 @@
-use crate::LOGFILE;
 use crate::dll_classifier::RandoVersion;
-use crate::dll_management::{OriDll, OriDllKind, search_game_dir};
-use crate::orirando::check_version;
-use crate::settings::Settings;
+use crate::dll_management::{InstallError, NotAnOriInstall, OriDll, OriDllKind, search_game_dir};
+use crate::game::{NetworkLocationUnavailable, is_original_game};
+use crate::lang::{set_language, tr};
+use crate::logging::{existing_log_file, set_log_level};
+use crate::orirando::{NetworkError, check_release_date, check_version};
+use crate::self_update::ExeDirNotWritable;
+use crate::settings::{LaunchType, Settings, take_save_error};
 use color_eyre::Result;
-use color_eyre::eyre::eyre;
+use color_eyre::eyre::{Report, eyre};
 use eframe::NativeOptions;
 use eframe::egui::{
     Align, Button, CentralPanel, Color32, Context, Frame, IconData, Id, InnerResponse, Layout,
-    Margin, Modal, Sides, Theme, ThemePreference, Ui, UiBuilder, ViewportBuilder, ViewportCommand,
+    Margin, Modal, Sides, Theme, ThemePreference, Ui, UiBuilder, UserAttentionType,
+    ViewportBuilder, ViewportCommand, WidgetInfo, WidgetType, WindowLevel,
 };
 use image::{ImageFormat, load_from_memory_with_format};
 use opener::reveal;
-use std::mem;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, Weak};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{Metadata, Span, debug, error, info, info_span, instrument, warn};
+use tray_icon::TrayIcon;
 
 mod app_settings;
+mod developer;
+mod export;
+mod fingerprint;
 mod game_settings;
+mod history;
+mod managed_backup;
+mod progress;
 mod rando;
+mod report;
+mod steam_verify;
+mod tray;
+mod uninstall;
 mod version_row;
+mod watcher;
+mod welcome;
+
+pub const WINDOW_TITLE: &str = "Ori DE Randomizer";
 
 #[instrument(skip(settings))]
-pub fn run_gui(settings: Settings) -> Result<()> {
-    let icon = load_from_memory_with_format(include_bytes!("../icon.ico"), ImageFormat::Ico)
-        .expect("invalid icon file");
-    let icon = IconData {
-        width: icon.width(),
-        height: icon.height(),
-        rgba: icon.into_rgba8().into_vec(),
-    };
+pub fn run_gui(settings: Settings, not_writable: Option<ExeDirNotWritable>) -> Result<()> {
+    let icon = load_icon();
 
+    let mut viewport = ViewportBuilder::default().with_inner_size([300., 250.]);
+    if let Some(icon) = &icon {
+        viewport = viewport.with_icon(icon.clone());
+    }
+    if settings.always_on_top {
+        viewport = viewport.with_always_on_top();
+    }
     let options = NativeOptions {
         centered: true,
-        viewport: ViewportBuilder::default()
-            .with_inner_size([300., 250.])
-            .with_icon(icon),
+        viewport,
         ..Default::default()
     };
 
     let result = eframe::run_native(
-        "Ori DE Randomizer",
+        WINDOW_TITLE,
         options,
         Box::new(|cc| {
-            adjust_themes(&cc.egui_ctx);
+            adjust_themes(&cc.egui_ctx, settings.accent_color);
+            set_language(settings.language);
             cc.egui_ctx.set_theme(settings.theme_preference);
-            Ok(Box::new(App::new(settings, cc.egui_ctx.clone())))
+            let app = App::new(settings, cc.egui_ctx.clone(), icon);
+            if let Some(not_writable) = not_writable {
+                app.inner.lock().unwrap().error_message = Some(format!(
+                    "{}\n{}",
+                    tr("self_update_not_writable"),
+                    not_writable.dir.display()
+                ));
+            }
+            Ok(Box::new(app))
         }),
     );
 
     result.map_err(|e| eyre!("Error running gui: {e:?}"))
 }
 
+/// A broken icon shouldn't keep the installer from working, so it's only logged.
+fn load_icon() -> Option<IconData> {
+    match load_from_memory_with_format(include_bytes!("../icon.ico"), ImageFormat::Ico) {
+        Ok(icon) => Some(IconData {
+            width: icon.width(),
+            height: icon.height(),
+            rgba: icon.into_rgba8().into_vec(),
+        }),
+        Err(err) => {
+            error!(?err, "Invalid icon file, continuing without icon");
+            None
+        }
+    }
+}
+
 struct App {
     inner: Arc<Mutex<Inner>>,
+    icon: Option<IconData>,
+    tray: Option<TrayIcon>,
 }
 
 impl App {
-    fn new(settings: Settings, egui_ctx: Context) -> App {
+    fn new(settings: Settings, egui_ctx: Context, icon: Option<IconData>) -> App {
         let app = Self {
             inner: Arc::new(Mutex::new(Inner::new(settings))),
+            icon,
+            tray: None,
         };
 
+        tray::set_menu_event_handler(Arc::downgrade(&app.inner));
+
         let mut inner = app.inner.lock().unwrap();
         inner.weak_self = Arc::downgrade(&app.inner);
         inner.egui_ctx = egui_ctx;
         inner.update_dlls();
+        inner.watch_managed_dir();
         inner.check_newest();
         drop(inner);
 
@@ -85,11 +135,36 @@ struct Inner {
     active_screen: ActiveScreen,
     current_dll: Option<OriDll>,
     all_dlls: Vec<OriDll>,
+    stray_dlls: Vec<PathBuf>,
     newest_version_installed: InstalledState,
     newest_version_available: NewestState,
+    newest_release_date: Option<String>,
     modal_message: Option<String>,
+    /// Checklist of the operation behind `modal_message`, empty for single step operations.
+    modal_steps: Vec<progress::ModalStep>,
     error_message: Option<String>,
     modal_uis: Vec<(AppModal, Box<DynModalUi>)>,
+    restore_from_tray: bool,
+    restore_offered: bool,
+    was_focused: bool,
+    update_dlls_generation: u64,
+    check_newest_generation: u64,
+    managed_watcher: Option<watcher::ManagedWatcher>,
+    last_busy: Option<Instant>,
+    settings_save_error: Option<String>,
+    launch_type_status: Option<((LaunchType, PathBuf), bool)>,
+    cloud_sync_warning_dismissed: bool,
+    /// The saved game dir that disappeared without detection finding another one.
+    /// It's kept, e.g. a removable drive might just not be plugged in.
+    missing_game_dir: Option<PathBuf>,
+    current_dll_hash: Option<String>,
+    current_dll_hash_generation: u64,
+    newest_checked_at: Option<Instant>,
+    close_requested_while_busy: bool,
+    force_close: bool,
+    last_install: Option<Instant>,
+    developer_hashes: HashMap<PathBuf, String>,
+    developer_hashes_generation: u64,
 }
 
 #[derive(Default, Eq, PartialEq)]
@@ -98,8 +173,18 @@ enum InstalledState {
     Unknown,
     Checking,
     None,
-    InstalledUnknown,
+    /// Only builds of unknown version are installed, e.g. custom builds.
+    /// Their file name and date are shown instead of a version.
+    InstalledUnknown {
+        display_name: String,
+        modified: Option<SystemTime>,
+    },
     Installed(RandoVersion),
+    /// The game dir contains the original game, which the randomizer doesn't support.
+    OriginalGame,
+    /// There's no game dll at all, e.g. on a fresh install that was never started,
+    /// so installing wouldn't leave a vanilla dll to restore.
+    MissingDll,
 }
 
 #[derive(Default, Debug, Eq, PartialEq)]
@@ -108,6 +193,7 @@ enum NewestState {
     Unknown,
     Checking,
     Error,
+    SiteUnavailable,
     Version(RandoVersion),
 }
 
@@ -155,19 +241,35 @@ impl AppModal {
 
 impl Inner {
     fn new(settings: Settings) -> Self {
-        Self {
+        let mut app = Self {
             settings: settings.clone(),
             prev_settings: settings,
             ..Self::default()
+        };
+        if !app.settings.seen_welcome {
+            app.show_welcome();
         }
+        app
     }
 }
 
 impl eframe::App for App {
     #[instrument(skip(self, ctx, _frame))]
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        let mut app = self.inner.lock().unwrap();
+        let inner = self.inner.clone();
+        let mut app = inner.lock().unwrap();
         app.render(ctx);
+        self.update_tray(&mut app, ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Remove the tray icon right away, so it doesn't linger after the window is gone
+        self.tray = None;
+
+        let app = self.inner.lock().unwrap();
+        if app.settings.restore_vanilla_on_exit {
+            app.restore_vanilla_on_exit();
+        }
     }
 }
 
@@ -187,10 +289,17 @@ impl Inner {
             let padding = ui.style().spacing.interact_size.y as _;
 
             let frame = Frame::popup(ui.style())
-                .fill(self.theme_color(
-                    Color32::from_rgb(255, 102, 102),
-                    Color32::from_rgb(122, 0, 0),
-                ))
+                .fill(if self.settings.color_blind_palette {
+                    self.theme_color(
+                        Color32::from_rgb(255, 176, 102),
+                        Color32::from_rgb(122, 61, 0),
+                    )
+                } else {
+                    self.theme_color(
+                        Color32::from_rgb(255, 102, 102),
+                        Color32::from_rgb(122, 0, 0),
+                    )
+                })
                 .inner_margin(Margin {
                     left: padding,
                     right: padding,
@@ -203,7 +312,7 @@ impl Inner {
                 Modal::new(Id::new("error modal"))
                     .frame(frame)
                     .show(&self.egui_ctx, |ui| {
-                        ui.heading("Error");
+                        ui.heading(tr("error"));
                         ui.label(msg);
                         ui.label("");
                         Sides::new()
@@ -211,8 +320,9 @@ impl Inner {
                                 ui,
                                 |ui| {
                                     Self::draw_show_log_button(ui);
+                                    self.draw_report_problem_button(ui, Some(msg));
                                 },
-                                |ui| ui.button("Ok").clicked(),
+                                |ui| ui.button(tr("ok")).clicked(),
                             )
                             .1
                     });
@@ -226,27 +336,66 @@ impl Inner {
 
 impl Inner {
     fn render(&mut self, ctx: &Context) {
+        let focused = ctx.input(|i| i.focused);
+        if focused && !self.was_focused {
+            self.recheck_game_dir();
+        }
+        self.was_focused = focused;
+
+        if self.is_busy() {
+            self.last_busy = Some(Instant::now());
+        }
+
+        if let Some(err) = take_save_error() {
+            self.settings_save_error = Some(err);
+        }
+
+        self.schedule_update_check(ctx);
+        self.intercept_close_while_busy(ctx);
+
         CentralPanel::default().show(ctx, |ui| {
             top_right(ui, |ui| {
-                ui.toggle_value(&mut self.show_settings, "⛭").on_hover_text("Settings");
+                // Glyph-only widgets get their hover text as accessible name, for screen readers
+                let toggle = ui
+                    .toggle_value(&mut self.show_settings, "⛭")
+                    .on_hover_text(tr("settings"));
+                let show_settings = self.show_settings;
+                toggle.widget_info(|| {
+                    WidgetInfo::selected(
+                        WidgetType::SelectableLabel,
+                        true,
+                        show_settings,
+                        tr("settings"),
+                    )
+                });
             });
 
             ui.vertical_centered(|ui| {
                 ui.heading("Ori DE Randomizer");
             });
 
+            self.draw_settings_save_warning(ui);
+            self.draw_cloud_sync_warning(ui);
+            self.draw_missing_game_dir_warning(ui);
+
             if !self.settings.game_dir.is_set() {
-                ui.label("Installation of Ori and the Blind Forest: Definitive Edition not found.");
-                ui.label("Note: The randomizer is only compatible with the Definitive Edition, not the original.");
+                ui.label(tr("game_not_found"));
+                ui.label(tr("de_only_note"));
                 ui.horizontal_wrapped(|ui| {
-                    ui.label("Please select the installation directory:");
+                    ui.label(tr("select_install_dir"));
                     self.draw_choose_game_dir_button(ui);
+                    if ui.button(tr("auto_detect")).clicked() {
+                        self.auto_detect_game_dir();
+                    }
                 });
             } else if self.show_settings {
                 self.draw_settings_ui(ui);
             } else {
                 self.draw_rando_version(ui);
-                if matches!(self.newest_version_installed, InstalledState::InstalledUnknown | InstalledState::Installed(_)) {
+                if matches!(
+                    self.newest_version_installed,
+                    InstalledState::InstalledUnknown { .. } | InstalledState::Installed(_)
+                ) {
                     self.draw_main_ui(ui);
                 }
             }
@@ -268,12 +417,42 @@ impl Inner {
             }
 
             if let Some(msg) = &self.modal_message {
-                Modal::new(Id::new("modal message")).show(ctx, |ui| {
+                let close_requested = self.close_requested_while_busy;
+                let resp = Modal::new(Id::new("modal message")).show(ctx, |ui| {
                     ui.vertical_centered(|ui| {
                         ui.label(msg);
                         ui.spinner();
-                    });
+                        self.draw_modal_steps(ui);
+
+                        if close_requested {
+                            ui.label("");
+                            ui.label(tr("close_while_busy"));
+                            ui.horizontal(|ui| {
+                                if ui.button(tr("keep_waiting")).clicked() {
+                                    return Some(false);
+                                }
+                                ui.button(tr("quit_anyway")).clicked().then_some(true)
+                            })
+                            .inner
+                        } else {
+                            None
+                        }
+                    })
+                    .inner
                 });
+
+                match resp.inner {
+                    Some(true) => {
+                        warn!("Quitting while an operation is in progress");
+                        self.force_close = true;
+                        ctx.send_viewport_cmd(ViewportCommand::Close);
+                    }
+                    Some(false) => self.close_requested_while_busy = false,
+                    None => {}
+                }
+            } else {
+                self.close_requested_while_busy = false;
+                self.modal_steps.clear();
             }
 
             self.draw_error_modal(ui);
@@ -282,21 +461,58 @@ impl Inner {
         if self.settings != self.prev_settings {
             if self.settings.game_dir != self.prev_settings.game_dir {
                 self.update_dlls();
+                self.watch_managed_dir();
+                self.cloud_sync_warning_dismissed = false;
+            }
+
+            if self.settings.log_level != self.prev_settings.log_level {
+                set_log_level(self.settings.log_level);
+            }
+            if self.settings.accent_color != self.prev_settings.accent_color {
+                adjust_themes(ctx, self.settings.accent_color);
+            }
+            if self.settings.always_on_top != self.prev_settings.always_on_top {
+                ctx.send_viewport_cmd(ViewportCommand::WindowLevel(
+                    if self.settings.always_on_top {
+                        WindowLevel::AlwaysOnTop
+                    } else {
+                        WindowLevel::Normal
+                    },
+                ));
             }
 
             self.prev_settings = self.settings.clone();
             self.settings.save_async();
             ctx.options_mut(|o| o.theme_preference = self.settings.theme_preference);
+            set_language(self.settings.language);
+        }
+    }
+
+    /// Closing the window while dlls are being written could leave the game broken,
+    /// so the user has to confirm it explicitly.
+    fn intercept_close_while_busy(&mut self, ctx: &Context) {
+        if ctx.input(|i| i.viewport().close_requested())
+            && self.modal_message.is_some()
+            && !self.force_close
+        {
+            info!("Close requested while an operation is in progress, asking for confirmation");
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            self.close_requested_while_busy = true;
         }
     }
 
     fn draw_main_ui(&mut self, ui: &mut Ui) {
+        if self.settings.compact_mode {
+            self.draw_rando_ui(ui);
+            return;
+        }
+
         ui.horizontal_wrapped(|ui| {
-            ui.selectable_value(&mut self.active_screen, ActiveScreen::Rando, "Rando");
+            ui.selectable_value(&mut self.active_screen, ActiveScreen::Rando, tr("rando"));
             ui.selectable_value(
                 &mut self.active_screen,
                 ActiveScreen::GameSettings,
-                "Game Settings",
+                tr("tab_game_settings"),
             );
         });
 
@@ -310,9 +526,71 @@ impl Inner {
         }
     }
 
+    /// Non-blocking, as the app works fine otherwise. Shown only once, until dismissed.
+    fn draw_settings_save_warning(&mut self, ui: &mut Ui) {
+        let Some(err) = &self.settings_save_error else {
+            return;
+        };
+
+        let mut dismissed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.colored_label(ui.visuals().warn_fg_color, tr("settings_not_saved"))
+                .on_hover_text(err);
+
+            let button = ui.small_button("✖").on_hover_text(tr("dismiss"));
+            button.widget_info(|| WidgetInfo::labeled(WidgetType::Button, true, tr("dismiss")));
+            dismissed = button.clicked();
+        });
+
+        if dismissed {
+            self.settings_save_error = None;
+        }
+    }
+
+    /// Advisory only, sync clients don't always cause problems.
+    fn draw_cloud_sync_warning(&mut self, ui: &mut Ui) {
+        if self.cloud_sync_warning_dismissed || !self.settings.game_dir.is_set() {
+            return;
+        }
+        let Some(provider) = self.settings.game_dir.cloud_sync_provider() else {
+            return;
+        };
+
+        ui.horizontal_wrapped(|ui| {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("{} ({provider})", tr("cloud_sync_warning")),
+            );
+
+            let button = ui.small_button("✖").on_hover_text(tr("dismiss"));
+            button.widget_info(|| WidgetInfo::labeled(WidgetType::Button, true, tr("dismiss")));
+            if button.clicked() {
+                self.cloud_sync_warning_dismissed = true;
+            }
+        });
+    }
+
+    /// Only shown while the missing game dir is still the configured one.
+    fn draw_missing_game_dir_warning(&mut self, ui: &mut Ui) {
+        if self.missing_game_dir.as_ref() != Some(&self.settings.game_dir.install) {
+            return;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.colored_label(ui.visuals().warn_fg_color, tr("game_dir_missing"))
+                .on_hover_text(self.settings.game_dir.install.to_string_lossy());
+
+            let button = ui.small_button("✖").on_hover_text(tr("dismiss"));
+            button.widget_info(|| WidgetInfo::labeled(WidgetType::Button, true, tr("dismiss")));
+            if button.clicked() {
+                self.missing_game_dir = None;
+            }
+        });
+    }
+
     fn draw_show_log_button(ui: &mut Ui) {
-        if let Some(path) = LOGFILE.get() {
-            if ui.button("Show logs").clicked() {
+        if let Some(path) = existing_log_file() {
+            if ui.button(tr("show_logs")).clicked() {
                 let result = reveal(path);
                 if let Err(err) = result {
                     error!(?err, "Couldn't show log file");
@@ -323,26 +601,33 @@ impl Inner {
 
     fn draw_bottom_row(&mut self, ui: &mut Ui) {
         bottom_left(ui, |ui| {
-            #[allow(clippy::collapsible_else_if)]
             if ui.ctx().theme() == Theme::Dark {
-                if ui
+                let button = ui
                     .add(Button::new("☀").frame(false))
-                    .on_hover_text("Switch to light mode")
-                    .clicked()
-                {
+                    .on_hover_text(tr("switch_to_light"));
+                button.widget_info(|| {
+                    WidgetInfo::labeled(WidgetType::Button, true, tr("switch_to_light"))
+                });
+                if button.clicked() {
                     self.settings.theme_preference = ThemePreference::Light;
                 }
             } else {
-                if ui
+                let button = ui
                     .add(Button::new("🌙").frame(false))
-                    .on_hover_text("Switch to dark mode")
-                    .clicked()
-                {
+                    .on_hover_text(tr("switch_to_dark"));
+                button.widget_info(|| {
+                    WidgetInfo::labeled(WidgetType::Button, true, tr("switch_to_dark"))
+                });
+                if button.clicked() {
                     self.settings.theme_preference = ThemePreference::Dark;
                 }
             }
 
-            if ui.button("Launch game").clicked() {
+            if ui
+                .add_enabled(!self.is_busy(), Button::new(tr("launch_game")))
+                .on_disabled_hover_text(tr("launch_disabled_busy"))
+                .clicked()
+            {
                 self.settings
                     .game_dir
                     .launch_game(self.settings.launch_type);
@@ -350,7 +635,7 @@ impl Inner {
         });
 
         bottom_right(ui, |ui| {
-            if ui.button("Close").clicked() {
+            if ui.button(tr("close")).clicked() {
                 ui.ctx().send_viewport_cmd(ViewportCommand::Close);
             }
         });
@@ -358,6 +643,37 @@ impl Inner {
 }
 
 impl Inner {
+    /// Whether dlls are currently being installed or scanned.
+    fn is_busy(&self) -> bool {
+        self.modal_message.is_some() || self.newest_version_installed == InstalledState::Checking
+    }
+
+    /// The custom accent color, or the default one of the current theme.
+    fn accent_color(&self) -> Color32 {
+        match self.settings.accent_color {
+            Some([r, g, b]) => Color32::from_rgb(r, g, b),
+            None => self.theme_color(Color32::LIGHT_BLUE, Color32::from_rgb(77, 140, 156)),
+        }
+    }
+
+    /// Red, or orange with the color-blind palette.
+    fn error_color(&self) -> Color32 {
+        if self.settings.color_blind_palette {
+            Color32::from_rgb(213, 94, 0)
+        } else {
+            Color32::RED
+        }
+    }
+
+    /// Green, or blue with the color-blind palette.
+    fn success_color(&self) -> Color32 {
+        if self.settings.color_blind_palette {
+            Color32::from_rgb(86, 180, 233)
+        } else {
+            Color32::GREEN
+        }
+    }
+
     fn theme_color(&self, light: Color32, dark: Color32) -> Color32 {
         if self.egui_ctx.theme() == Theme::Light {
             light
@@ -392,6 +708,33 @@ impl Inner {
         });
     }
 
+    /// Like [`Self::run_off_thread`], but only the result of the latest request of an operation is applied.
+    ///
+    /// Every call bumps the generation counter returned by `generation`.
+    /// Invariant: a sync callback only runs if no newer request of the same operation was started in the meantime,
+    /// sync callbacks of superseded requests are ignored.
+    fn run_latest_off_thread<C, S, R>(
+        &mut self,
+        generation: fn(&mut Self) -> &mut u64,
+        calc: C,
+        sync: S,
+    ) where
+        C: (FnOnce() -> R) + Send + 'static,
+        S: (FnOnce(&mut Self, R)) + Send + 'static,
+    {
+        let counter = generation(self);
+        *counter = counter.wrapping_add(1);
+        let this_generation = *counter;
+
+        self.run_off_thread(calc, move |app, value| {
+            if *generation(app) == this_generation {
+                sync(app, value);
+            } else {
+                debug!("Operation was superseded, discarding result");
+            }
+        });
+    }
+
     #[instrument(skip(self))]
     fn update_dlls(&mut self) {
         if !self.settings.game_dir.is_set() {
@@ -399,25 +742,22 @@ impl Inner {
             return;
         }
 
-        if mem::replace(&mut self.newest_version_installed, InstalledState::Checking)
-            == InstalledState::Checking
-        {
-            warn!("Tried to update dlls, while an update is already in progress. Aborting.");
-            return;
+        if self.newest_version_installed == InstalledState::Checking {
+            debug!("Updating dlls while an update is already in progress, superseding it");
         }
+        self.newest_version_installed = InstalledState::Checking;
 
         info!("Updating dlls...");
 
         let game_dir = self.settings.game_dir.clone();
-        self.run_off_thread(
-            move || {
-                let (current, all) = match search_game_dir(&game_dir) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!(?e, "Couldn't update dlls");
-                        return None;
-                    }
-                };
+        self.run_latest_off_thread(
+            |app| &mut app.update_dlls_generation,
+            move || -> Result<_> {
+                let (current, all, stray) = search_game_dir(&game_dir)?;
+                // Not the same as `current` being `None`, that's also the case for a broken dll
+                let active_dll = game_dir.resolved_managed().join("Assembly-CSharp.dll");
+                let active_missing = matches!(std::fs::exists(&active_dll), Ok(false));
+                let has_vanilla = all.iter().any(|dll| dll.kind == OriDllKind::Vanilla);
 
                 let newest = {
                     let newest_known = all
@@ -428,69 +768,174 @@ impl Inner {
                         })
                         .max();
 
-                    let has_unknown = all
+                    // The active build is the most relevant one, otherwise the latest one
+                    let is_unknown =
+                        |dll: &&OriDll| matches!(dll.kind, OriDllKind::UnknownRando(_));
+                    let unknown = current
                         .iter()
-                        .any(|dll| matches!(dll.kind, OriDllKind::UnknownRando(_)));
+                        .find(is_unknown)
+                        .or_else(|| all.iter().filter(is_unknown).max_by_key(|dll| dll.modified));
 
-                    match (newest_known, has_unknown) {
+                    match (newest_known, unknown) {
                         (Some(v), _) => InstalledState::Installed(v),
-                        (None, true) => InstalledState::InstalledUnknown,
+                        (None, Some(unknown)) => InstalledState::InstalledUnknown {
+                            display_name: unknown.display_name.clone(),
+                            modified: unknown.modified,
+                        },
+                        _ if active_missing && !has_vanilla => InstalledState::MissingDll,
                         _ => InstalledState::None,
                     }
                 };
 
-                Some((current, all, newest))
+                if is_original_game(&game_dir) {
+                    info!("Game dir contains the original game");
+                    return Ok((current, all, stray, InstalledState::OriginalGame));
+                }
+
+                Ok((current, all, stray, newest))
             },
             |app, dlls| {
-                let Some((current, all, newest)) = dlls else {
-                    app.newest_version_installed = InstalledState::None;
-                    app.error_message = Some("Failed to load installed versions".into());
-                    return;
+                let (current, all, stray, newest) = match dlls {
+                    Ok(dlls) => dlls,
+                    Err(err) => {
+                        error!(?err, "Couldn't update dlls");
+                        app.newest_version_installed = InstalledState::None;
+                        app.error_message =
+                            Some(install_error_message(tr("failed_load_versions"), &err));
+                        return;
+                    }
                 };
 
                 info!("Updated dlls");
                 app.current_dll = current;
                 app.all_dlls = all;
+                app.stray_dlls = stray;
                 app.newest_version_installed = newest;
+                app.update_current_hash();
+                app.check_partial_install();
+                // Shown on top of the restore offer, as it explains why the dll is missing
+                app.check_quarantined_dll();
             },
         );
     }
 
+    /// Checks again once the configured interval has passed since the last check.
+    /// Driven by repaints, so it stops together with the app.
+    fn schedule_update_check(&mut self, ctx: &Context) {
+        let interval = self.settings.update_check_interval;
+        if interval == 0 || self.newest_version_available == NewestState::Checking {
+            return;
+        }
+        let Some(checked_at) = self.newest_checked_at else {
+            return;
+        };
+
+        let interval = Duration::from_secs(interval.saturating_mul(60));
+        let elapsed = checked_at.elapsed();
+        if elapsed >= interval {
+            info!("Periodic update check");
+            self.check_newest();
+        } else {
+            ctx.request_repaint_after(interval - elapsed);
+        }
+    }
+
     #[instrument(skip(self))]
     fn check_newest(&mut self) {
+        let previous = match self.newest_version_available {
+            NewestState::Version(version) => Some(version),
+            _ => None,
+        };
         self.newest_version_available = NewestState::Checking;
+        self.newest_checked_at = Some(Instant::now());
 
         info!("Checking for newest dll available");
-        self.run_off_thread(
-            || match check_version() {
-                Ok(v) => NewestState::Version(v),
+        let cache = self.settings.version_cache.clone();
+        self.run_latest_off_thread(
+            |app| &mut app.check_newest_generation,
+            move || match check_version(cache.as_ref()) {
+                Ok(cache) => (
+                    NewestState::Version(cache.version),
+                    check_release_date(),
+                    Some(cache),
+                ),
                 Err(err) => {
                     error!(?err, "Failed to check newest available version");
-                    NewestState::Error
+                    if matches!(err, NetworkError::SiteUnavailable { .. }) {
+                        (NewestState::SiteUnavailable, None, None)
+                    } else {
+                        (NewestState::Error, None, None)
+                    }
                 }
             },
-            |app, newest| {
-                info!(?newest, "Retrieved newest version available");
+            |app, (newest, release_date, cache)| {
+                info!(?newest, ?release_date, "Retrieved newest version available");
+                let released_meanwhile = matches!(
+                    (previous, &newest),
+                    (Some(previous), NewestState::Version(newest)) if *newest > previous
+                );
+                if released_meanwhile {
+                    info!(?previous, "New version released while the app was open");
+                    app.egui_ctx
+                        .send_viewport_cmd(ViewportCommand::RequestUserAttention(
+                            UserAttentionType::Informational,
+                        ));
+                }
                 app.newest_version_available = newest;
+                app.newest_release_date = release_date;
+                if cache.is_some() {
+                    app.settings.version_cache = cache;
+                    app.settings.last_version_check = Some(SystemTime::now());
+                }
             },
         );
     }
 }
 
-fn adjust_themes(ctx: &Context) {
+/// Adjusts the built-in themes, `accent` replaces the default selection stroke if set.
+fn adjust_themes(ctx: &Context, accent: Option<[u8; 3]>) {
+    let accent = accent.map(|[r, g, b]| Color32::from_rgb(r, g, b));
+
     ctx.style_mut_of(Theme::Light, |style| {
         style.visuals.widgets.noninteractive.fg_stroke.color = Color32::from_gray(30);
         style.visuals.widgets.inactive.fg_stroke.color = Color32::from_gray(30);
-        style.visuals.selection.stroke.color = Color32::from_gray(15);
+        style.visuals.selection.stroke.color = accent.unwrap_or(Color32::from_gray(15));
     });
 
     ctx.style_mut_of(Theme::Dark, |style| {
         style.visuals.widgets.noninteractive.fg_stroke.color = Color32::from_gray(235);
         style.visuals.widgets.inactive.fg_stroke.color = Color32::from_gray(235);
-        style.visuals.selection.stroke.color = Color32::from_gray(245);
+        style.visuals.selection.stroke.color = accent.unwrap_or(Color32::from_gray(245));
     });
 }
 
+/// Error message for a failed install or scan of the game dir.
+/// A lack of disk space or an unavailable network share is spelled out, as that's something the user can fix,
+/// and so is a vanilla download, as that's something the user should report.
+fn install_error_message(failed: &str, err: &Report) -> String {
+    match err.downcast_ref::<InstallError>() {
+        Some(InstallError::NotEnoughDiskSpace(disk_space)) => {
+            return format!("{failed}\n{disk_space}");
+        }
+        Some(InstallError::NetworkLocationUnavailable(_)) => {
+            return format!("{failed}\n{}", tr("network_location_unavailable"));
+        }
+        _ => {}
+    }
+    // Scans aren't installs, they fail with the bare error
+    if err.downcast_ref::<NetworkLocationUnavailable>().is_some() {
+        return format!("{failed}\n{}", tr("network_location_unavailable"));
+    }
+    if err.downcast_ref::<NotAnOriInstall>().is_some() {
+        return format!("{failed}\n{}", tr("not_an_ori_install"));
+    }
+    if let Some(vanilla @ NetworkError::ServerReturnedVanilla) = err.downcast_ref::<NetworkError>()
+    {
+        return format!("{failed}\n{vanilla}");
+    }
+    failed.to_owned()
+}
+
 fn open_file_button(ui: &mut Ui, button_text: &str, get_path: impl Fn() -> PathBuf) {
     if ui
         .button(button_text)
@@ -510,6 +955,13 @@ fn open_file(path: &Path) {
     }
 }
 
+#[instrument]
+fn reveal_file(path: &Path) {
+    if let Err(err) = reveal(path) {
+        error!(?err, "Could not reveal file");
+    }
+}
+
 /// Like `ui.scope(add_contents)` but forgets the size of the contents.
 /// So any widgets added to `ui` after this call will behave exactly the same way as if `forgetful_scope` wasn't called.
 /// Be careful: This makes it easy to have multiple widgets overlap each other.
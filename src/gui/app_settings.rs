@@ -1,52 +1,216 @@
-use crate::game::{GameDir, search_for_game_dir, verify_game_dir};
-use crate::gui::{AppModal, Inner};
+use crate::dll_management::BackupLocation;
+use crate::game::{
+    GameDir, GameSource, search_for_game_dir, search_for_game_dir_in, search_game_dir_below,
+    verify_game_dir,
+};
+use crate::gui::{AppModal, Inner, reveal_file};
+use crate::lang::{Language, tr};
+use crate::logging::LogLevel;
 use crate::settings::LaunchType;
-use eframe::egui::{Align, ComboBox, Layout, Ui};
+use eframe::egui::{Align, Button, ComboBox, DragValue, Layout, ThemePreference, Ui};
 use rfd::FileDialog;
-use tracing::instrument;
+use tracing::{info, instrument, warn};
 
 impl Inner {
     #[instrument(skip(self, ui))]
     pub(super) fn draw_settings_ui(&mut self, ui: &mut Ui) {
         ui.vertical_centered(|ui| {
-            ui.label(format!("version {}", env!("CARGO_PKG_VERSION")));
+            ui.label(format!("{} {}", tr("version"), env!("CARGO_PKG_VERSION")));
         });
 
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
-                ui.label("Theme");
-                self.settings.theme_preference.radio_buttons(ui);
+                ui.label(tr("theme"));
+                let theme = &mut self.settings.theme_preference;
+                ui.radio_value(theme, ThemePreference::System, tr("theme_system"));
+                ui.radio_value(theme, ThemePreference::Dark, tr("theme_dark"));
+                ui.radio_value(theme, ThemePreference::Light, tr("theme_light"));
             });
 
+            self.draw_accent_color_setting(ui);
+            self.draw_language_setting(ui);
+
             self.draw_game_dir_setting(ui);
             self.draw_launch_type_setting(ui);
 
             ui.horizontal_wrapped(|ui| {
-                ui.label("Auto-Update");
+                ui.label(tr("auto_update"));
                 ui.checkbox(&mut self.settings.self_update, "");
             });
 
-            Self::draw_show_log_button(ui);
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("launch_after"));
+                ui.checkbox(
+                    &mut self.settings.launch_after_install,
+                    tr("launch_after_install"),
+                );
+                ui.checkbox(
+                    &mut self.settings.launch_after_switch,
+                    tr("launch_after_switch"),
+                );
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("minimize_to_tray"));
+                ui.checkbox(&mut self.settings.minimize_to_tray, "");
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("always_on_top"));
+                ui.checkbox(&mut self.settings.always_on_top, "");
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("restore_vanilla_on_exit"));
+                ui.checkbox(&mut self.settings.restore_vanilla_on_exit, "")
+                    .on_hover_text(tr("restore_vanilla_on_exit_hover"));
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("compact_mode"));
+                ui.checkbox(&mut self.settings.compact_mode, "")
+                    .on_hover_text(tr("compact_mode_hover"));
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("developer_mode"));
+                ui.checkbox(&mut self.settings.developer_mode, "")
+                    .on_hover_text(tr("developer_mode_hover"));
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("color_blind_palette"));
+                ui.checkbox(&mut self.settings.color_blind_palette, "")
+                    .on_hover_text(tr("color_blind_palette_hover"));
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("max_backups"));
+                ui.add(DragValue::new(&mut self.settings.max_backups).range(1..=100));
+            });
+
+            self.draw_backup_location_setting(ui);
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("update_check_interval"));
+                ui.add(
+                    DragValue::new(&mut self.settings.update_check_interval)
+                        .range(0..=1440)
+                        .suffix(" min"),
+                )
+                .on_hover_text(tr("update_check_interval_hover"));
+            });
+
+            self.draw_log_level_setting(ui);
+
+            ui.horizontal_wrapped(|ui| {
+                Self::draw_show_log_button(ui);
+                self.draw_report_problem_button(ui, None);
+                self.draw_full_backup_button(ui);
+                self.draw_uninstall_button(ui);
+                self.draw_steam_verify_button(ui);
+            });
         });
     }
 
     fn draw_game_dir_setting(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
-            ui.label("Game installation directory");
+            ui.label(tr("game_install_dir"));
             ui.text_edit_singleline(&mut self.settings.game_dir.install.to_string_lossy());
         });
         ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
             self.draw_choose_game_dir_button(ui);
-            if ui.button("Auto-Detect").clicked() {
-                self.settings.game_dir = search_for_game_dir().unwrap_or_default();
-                self.settings.launch_type = LaunchType::Steam;
+            if ui
+                .add_enabled(self.settings.game_dir.is_set(), Button::new(tr("reveal")))
+                .clicked()
+            {
+                reveal_file(&self.settings.game_dir.install);
+            }
+            if ui.button(tr("auto_detect")).clicked() {
+                self.auto_detect_game_dir();
+            }
+            ui.menu_button(tr("detect_via"), |ui| {
+                for source in GameSource::ALL {
+                    if ui.button(source.to_string()).clicked() {
+                        ui.close_menu();
+                        self.detect_game_dir_in(source);
+                    }
+                }
+            });
+        });
+    }
+
+    fn draw_accent_color_setting(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("accent_color"));
+
+            let mut custom = self.settings.accent_color.is_some();
+            if ui.checkbox(&mut custom, tr("custom")).changed() {
+                // Start from the current color, so enabling alone doesn't change anything
+                let current = self.accent_color();
+                self.settings.accent_color =
+                    custom.then(|| [current.r(), current.g(), current.b()]);
             }
+
+            if let Some(rgb) = &mut self.settings.accent_color {
+                ui.color_edit_button_srgb(rgb);
+            }
+        });
+    }
+
+    fn draw_language_setting(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("language"));
+
+            ComboBox::from_id_salt("language_combo")
+                .selected_text(self.settings.language.to_string())
+                .show_ui(ui, |ui| {
+                    for language in Language::ALL {
+                        ui.selectable_value(
+                            &mut self.settings.language,
+                            language,
+                            language.to_string(),
+                        );
+                    }
+                });
+        });
+    }
+
+    fn draw_backup_location_setting(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("backup_location"));
+
+            ComboBox::from_id_salt("backup_location_combo")
+                .selected_text(backup_location_name(self.settings.backup_location))
+                .show_ui(ui, |ui| {
+                    for location in BackupLocation::ALL {
+                        ui.selectable_value(
+                            &mut self.settings.backup_location,
+                            location,
+                            backup_location_name(location),
+                        );
+                    }
+                });
+        });
+    }
+
+    fn draw_log_level_setting(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("log_level"));
+
+            ComboBox::from_id_salt("log_level_combo")
+                .selected_text(self.settings.log_level.to_string())
+                .show_ui(ui, |ui| {
+                    for level in LogLevel::ALL {
+                        ui.selectable_value(&mut self.settings.log_level, level, level.to_string());
+                    }
+                });
         });
     }
 
     fn draw_launch_type_setting(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
-            ui.label("Game launch type");
+            ui.label(tr("launch_type"));
 
             ComboBox::from_id_salt("launch_type_combo")
                 .selected_text(self.settings.launch_type.to_string())
@@ -62,17 +226,121 @@ impl Inner {
                         LaunchType::File.to_string(),
                     );
                 });
+
+            if !self.launch_type_available() {
+                let warning = match self.settings.launch_type {
+                    LaunchType::Steam => tr("steam_not_found"),
+                    LaunchType::File => tr("exe_not_found"),
+                };
+                ui.colored_label(ui.visuals().warn_fg_color, warning);
+            }
         });
     }
 
+    /// Checked only when the launch type or game dir changes, not every frame.
+    fn launch_type_available(&mut self) -> bool {
+        let key = (
+            self.settings.launch_type,
+            self.settings.game_dir.install.clone(),
+        );
+
+        match &self.launch_type_status {
+            Some((checked, available)) if *checked == key => *available,
+            _ => {
+                let available = self.settings.game_dir.can_launch(key.0);
+                self.launch_type_status = Some((key, available));
+                available
+            }
+        }
+    }
+
+    /// Keeps the current game dir if the game isn't found anywhere.
+    #[instrument(skip(self))]
+    pub(super) fn auto_detect_game_dir(&mut self) {
+        match search_for_game_dir() {
+            Some(game_dir) => {
+                self.settings.game_dir = game_dir;
+                self.settings.launch_type = LaunchType::Steam;
+            }
+            None => {
+                info!("Game not found");
+                self.error_message = Some(tr("game_not_found").to_owned());
+            }
+        }
+    }
+
+    /// Unlike the generic auto-detect, keeps the current game dir if `source` doesn't have the game.
+    #[instrument(skip(self))]
+    fn detect_game_dir_in(&mut self, source: GameSource) {
+        match search_for_game_dir_in(source) {
+            Some(game_dir) => {
+                self.settings.game_dir = game_dir;
+                self.settings.launch_type = source.launch_type();
+            }
+            None => {
+                info!("Game not found in source");
+                self.error_message = Some(format!("{} ({source})", tr("game_not_found")));
+            }
+        }
+    }
+
+    /// Searches for the game again if the configured game dir has disappeared,
+    /// e.g. because the game was moved or uninstalled while the app was open.
+    ///
+    /// Network game dirs aren't checked, probing an unreachable share would freeze the window.
+    /// An unreachable share isn't a missing game either, installs report it once they probe it off-thread.
+    #[instrument(skip(self))]
+    pub(super) fn recheck_game_dir(&mut self) {
+        let game_dir = &self.settings.game_dir;
+        if !game_dir.is_set() || game_dir.is_network_path() {
+            return;
+        }
+        if verify_game_dir(game_dir) {
+            self.missing_game_dir = None;
+            return;
+        }
+
+        warn!(game_dir=?game_dir.install, "Game dir disappeared, searching again");
+        match search_for_game_dir() {
+            Some(game_dir) => {
+                self.settings.game_dir = game_dir;
+                self.settings.launch_type = LaunchType::Steam;
+                self.missing_game_dir = None;
+            }
+            None => {
+                // Without a replacement, forgetting the saved path would only make the user pick it again
+                info!("Game not found, keeping the missing game dir");
+                self.missing_game_dir = Some(game_dir.install.clone());
+            }
+        }
+    }
+
     pub(super) fn draw_choose_game_dir_button(&mut self, ui: &mut Ui) {
-        if ui.button("Choose...").clicked() {
-            let dir = FileDialog::new().pick_folder();
-            if let Some(dir) = dir {
+        if ui.button(tr("choose")).clicked() {
+            let start_dir = if self.settings.game_dir.is_set() {
+                Some(&self.settings.game_dir.install)
+            } else {
+                self.settings.last_browsed_dir.as_ref()
+            };
+
+            let mut dialog = FileDialog::new();
+            if let Some(start_dir) = start_dir {
+                dialog = dialog.set_directory(start_dir);
+            }
+
+            if let Some(dir) = dialog.pick_folder() {
+                // Paths that aren't valid unicode can't be stored in the settings file
+                if dir.to_str().is_some() {
+                    self.settings.last_browsed_dir = Some(dir.clone());
+                }
+
                 let game_dir = GameDir::new(dir);
                 if verify_game_dir(&game_dir) {
                     self.settings.game_dir = game_dir;
                     self.settings.launch_type = LaunchType::File;
+                } else if let Some(below) = search_game_dir_below(&game_dir.install) {
+                    info!(?below.install, "Found the game below the picked folder");
+                    self.offer_game_dir_below(below);
                 } else {
                     self.show_invalid_game_dir_modal();
                 }
@@ -80,19 +348,42 @@ impl Inner {
         }
     }
 
+    /// The picked folder contains the game instead of being it, which is easily mixed up.
+    fn offer_game_dir_below(&mut self, game_dir: GameDir) {
+        let path = game_dir.install.display().to_string();
+        self.show_modal_ui(AppModal::new().dismissable(true), move |app, ui, modal| {
+            ui.label(tr("game_dir_found_below"));
+            ui.label(&path);
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("cancel")).clicked() {
+                    modal.close();
+                }
+                if ui.button(tr("use_this_folder")).clicked() {
+                    modal.close();
+                    app.settings.game_dir = game_dir.clone();
+                    app.settings.launch_type = LaunchType::File;
+                }
+            });
+        });
+    }
+
     fn show_invalid_game_dir_modal(&mut self) {
         self.show_modal_ui(AppModal::new().dismissable(true), move |_app, ui, modal| {
-            ui.label(
-                "The selected directory does not appear to be a valid installation of \
-                    Ori and the Blind Forest: Definitive Edition. \
-                    Please select another directory.",
-            );
+            ui.label(tr("invalid_game_dir"));
 
             ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
-                if ui.button("Okay").clicked() {
+                if ui.button(tr("okay")).clicked() {
                     modal.close();
                 }
             });
         });
     }
 }
+
+fn backup_location_name(location: BackupLocation) -> &'static str {
+    match location {
+        BackupLocation::Managed => tr("backup_location_managed"),
+        BackupLocation::AppData => tr("backup_location_app_data"),
+    }
+}
@@ -0,0 +1,67 @@
+use crate::dll_classifier::sha256_file;
+use crate::gui::Inner;
+use crate::lang::tr;
+use crate::orirando::SITE_URL;
+use eframe::egui::{CollapsingHeader, Grid, Ui};
+use std::collections::HashMap;
+use tracing::{instrument, warn};
+
+impl Inner {
+    /// Read-only internals for developers and support, only shown in developer mode.
+    pub(super) fn draw_developer_info(&mut self, ui: &mut Ui) {
+        CollapsingHeader::new(tr("developer_info"))
+            .id_salt("developer info")
+            .show(ui, |ui| {
+                ui.label(tr("managed_dir"));
+                ui.monospace(self.settings.game_dir.resolved_managed().to_string_lossy());
+
+                ui.label(tr("active_dll_hash"));
+                ui.monospace(self.current_dll_hash.as_deref().unwrap_or("-"));
+
+                ui.separator();
+                Grid::new("developer dlls").striped(true).show(ui, |ui| {
+                    for dll in &self.all_dlls {
+                        ui.monospace(dll.path.to_string_lossy());
+                        ui.monospace(format!("{:?}", dll.kind));
+                        ui.monospace(
+                            self.developer_hashes
+                                .get(&dll.path)
+                                .map_or("", String::as_str),
+                        );
+                        ui.end_row();
+                    }
+                });
+                if ui.button(tr("hash_all_dlls")).clicked() {
+                    self.hash_all_dlls();
+                }
+
+                ui.separator();
+                ui.label(tr("server_urls"));
+                ui.monospace(SITE_URL);
+                for mirror in &self.settings.dll_mirrors {
+                    ui.monospace(mirror);
+                }
+            });
+    }
+
+    #[instrument(skip(self))]
+    fn hash_all_dlls(&mut self) {
+        let paths: Vec<_> = self.all_dlls.iter().map(|dll| dll.path.clone()).collect();
+        self.run_latest_off_thread(
+            |app| &mut app.developer_hashes_generation,
+            move || {
+                paths
+                    .into_iter()
+                    .filter_map(|path| match sha256_file(&path) {
+                        Ok(hash) => Some((path, hash)),
+                        Err(err) => {
+                            warn!(?err, ?path, "Couldn't hash dll");
+                            None
+                        }
+                    })
+                    .collect::<HashMap<_, _>>()
+            },
+            |app, hashes| app.developer_hashes = hashes,
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use crate::dll_classifier::classify_dll;
+use crate::dll_management::{OriDll, OriDllKind};
+use crate::gui::Inner;
+use crate::lang::tr;
+use crate::orirando::download_dll;
+use color_eyre::Result;
+use color_eyre::eyre::{OptionExt, WrapErr};
+use eframe::egui::Ui;
+use rfd::FileDialog;
+use tracing::{error, info, instrument, warn};
+
+impl Inner {
+    /// Lets users hand the exact dll they're running to maintainers, e.g. for custom builds.
+    pub(super) fn draw_export_button(&mut self, ui: &mut Ui) {
+        let Some(current) = self.current_dll.clone() else {
+            return;
+        };
+
+        if ui.button(tr("export_dll")).clicked() {
+            self.export_dll(&current);
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub(super) fn export_dll(&mut self, dll: &OriDll) {
+        let Some(target) = FileDialog::new()
+            .set_file_name(dll.export_name())
+            .add_filter("DLL", &["dll"])
+            .save_file()
+        else {
+            return;
+        };
+
+        match std::fs::copy(&dll.path, &target) {
+            Ok(_) => info!(?target, "Exported dll"),
+            Err(err) => {
+                error!(?err, ?target, "Couldn't export dll");
+                self.error_message = Some(format!("{}\n{err}", tr("failed_export_dll")));
+            }
+        }
+    }
+
+    pub(super) fn draw_download_only_button(&mut self, ui: &mut Ui) {
+        if ui
+            .button(tr("download_only"))
+            .on_hover_text(tr("download_only_hover"))
+            .clicked()
+        {
+            self.download_only();
+        }
+    }
+
+    /// Downloads the latest dll and saves it where the user picks, without touching the game dir.
+    #[instrument(skip(self))]
+    fn download_only(&mut self) {
+        if let Some(modal_message) = &self.modal_message {
+            warn!(
+                ?modal_message,
+                "Some modal action is already in progress, doing nothing"
+            );
+            return;
+        }
+
+        info!("Downloading dll without installing it");
+        self.modal_message = Some(tr("downloading_dll").to_owned());
+
+        let mirrors = self.settings.dll_mirrors.clone();
+        self.run_off_thread(
+            move || -> Result<_> {
+                let dll = download_dll(&mirrors)?;
+                let kind = OriDllKind::from_classification(classify_dll(&dll))
+                    .ok_or_eyre("Downloaded file isn't an Ori dll")?;
+
+                // The dialog is opened here, the name depends on the version that was downloaded
+                let Some(target) = FileDialog::new()
+                    .set_file_name(kind.export_name())
+                    .add_filter("DLL", &["dll"])
+                    .save_file()
+                else {
+                    return Ok(None);
+                };
+                std::fs::write(&target, &dll).wrap_err("Error saving dll")?;
+                Ok(Some(target))
+            },
+            |app, result| {
+                app.modal_message = None;
+                match result {
+                    Ok(Some(target)) => info!(?target, "Saved downloaded dll"),
+                    Ok(None) => info!("Saving the downloaded dll was cancelled"),
+                    Err(err) => {
+                        error!(?err, "Couldn't download dll");
+                        app.error_message = Some(format!("{}\n{err}", tr("failed_download_only")));
+                    }
+                }
+            },
+        );
+    }
+}
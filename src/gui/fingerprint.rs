@@ -0,0 +1,47 @@
+use crate::dll_classifier::sha256_file;
+use crate::gui::Inner;
+use crate::lang::tr;
+use eframe::egui::{Ui, WidgetInfo, WidgetType};
+use tracing::{instrument, warn};
+
+/// Hex digits of the hash shown in the ui, the full hash can be copied.
+const FINGERPRINT_LENGTH: usize = 12;
+
+impl Inner {
+    /// Hashes the active dll off-thread, so users can verify they run exactly the expected build.
+    #[instrument(skip(self))]
+    pub(super) fn update_current_hash(&mut self) {
+        self.current_dll_hash = None;
+        let Some(path) = self.current_dll.as_ref().map(|dll| dll.path.clone()) else {
+            return;
+        };
+
+        self.run_latest_off_thread(
+            |app| &mut app.current_dll_hash_generation,
+            move || {
+                sha256_file(&path)
+                    .inspect_err(|err| warn!(?err, ?path, "Couldn't hash active dll"))
+                    .ok()
+            },
+            |app, hash| app.current_dll_hash = hash,
+        );
+    }
+
+    pub(super) fn draw_fingerprint(&self, ui: &mut Ui) {
+        let Some(hash) = &self.current_dll_hash else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let fingerprint = hash.get(..FINGERPRINT_LENGTH).unwrap_or(hash);
+            ui.weak(format!("SHA-256: {fingerprint}…"))
+                .on_hover_text(hash);
+
+            let button = ui.small_button("🗐").on_hover_text(tr("copy_hash"));
+            button.widget_info(|| WidgetInfo::labeled(WidgetType::Button, true, tr("copy_hash")));
+            if button.clicked() {
+                ui.ctx().copy_text(hash.clone());
+            }
+        });
+    }
+}
@@ -1,8 +1,10 @@
-use crate::gui::{Inner, open_file_button};
-use eframe::egui::Ui;
+use crate::gui::{AppModal, Inner, open_file};
+use crate::lang::tr;
+use eframe::egui::{Align, Layout, Ui};
 use std::env;
+use std::fs::File;
 use std::path::PathBuf;
-use tracing::instrument;
+use tracing::{error, info, instrument};
 
 impl Inner {
     pub(super) fn draw_game_settings_ui(&mut self, ui: &mut Ui) {
@@ -11,24 +13,61 @@ impl Inner {
     }
 
     #[instrument(skip_all)]
-    fn draw_open_files(&self, ui: &mut Ui) {
+    fn draw_open_files(&mut self, ui: &mut Ui) {
         ui.horizontal_wrapped(|ui| {
-            ui.label("Open settings:");
-            open_file_button(ui, "Randomizer", || {
-                self.rando_install_path("RandomizerSettings.txt")
-            });
+            ui.label(tr("open_settings"));
+            let path = self.rando_install_path("RandomizerSettings.txt");
+            self.draw_open_game_file_button(ui, tr("randomizer"), path);
         });
         ui.horizontal_wrapped(|ui| {
-            ui.label("Open Controls:");
-            open_file_button(ui, "Rando", || {
-                self.rando_install_path("RandomizerRebinding.txt")
-            });
-            open_file_button(ui, "Vanilla (KBM)", || game_app_path("KeyRebindings.txt"));
-            open_file_button(ui, "Vanilla (Controller)", || {
-                game_app_path("ControllerRebindings.txt")
-            });
-            open_file_button(ui, "Controller Remaps", || {
-                game_app_path("ControllerButtonRemaps.txt")
+            ui.label(tr("open_controls"));
+            let path = self.rando_install_path("RandomizerRebinding.txt");
+            self.draw_open_game_file_button(ui, tr("rando"), path);
+            let path = game_app_path("KeyRebindings.txt");
+            self.draw_open_game_file_button(ui, tr("vanilla_kbm"), path);
+            let path = game_app_path("ControllerRebindings.txt");
+            self.draw_open_game_file_button(ui, tr("vanilla_controller"), path);
+            let path = game_app_path("ControllerButtonRemaps.txt");
+            self.draw_open_game_file_button(ui, tr("controller_remaps"), path);
+        });
+    }
+
+    /// Like `open_file_button`, but the game only creates these files once it ran,
+    /// so instead of failing to open them, that gets explained.
+    fn draw_open_game_file_button(&mut self, ui: &mut Ui, button_text: &str, path: PathBuf) {
+        let clicked = ui
+            .button(button_text)
+            .on_hover_text(path.to_string_lossy())
+            .clicked();
+        if !clicked {
+            return;
+        }
+
+        if path.is_file() {
+            open_file(&path);
+            return;
+        }
+
+        info!(?path, "File to open doesn't exist yet");
+        self.show_modal_ui(AppModal::new().dismissable(true), move |app, ui, modal| {
+            ui.label(tr("file_not_created_yet"));
+            ui.monospace(path.to_string_lossy());
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("cancel")).clicked() {
+                    modal.close();
+                }
+                if ui.button(tr("create_empty_file")).clicked() {
+                    modal.close();
+                    match File::create_new(&path) {
+                        Ok(_) => open_file(&path),
+                        Err(err) => {
+                            error!(?err, ?path, "Couldn't create empty file");
+                            app.error_message =
+                                Some(format!("{}\n{err}", tr("failed_create_file")));
+                        }
+                    }
+                }
             });
         });
     }
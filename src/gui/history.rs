@@ -0,0 +1,84 @@
+use crate::dll_management::OriDllKind;
+use crate::gui::Inner;
+use crate::lang::tr;
+use eframe::egui::{CollapsingHeader, ScrollArea, Ui};
+use std::cmp::Reverse;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+impl Inner {
+    /// Lists the installed rando versions, newest first, to switch between them.
+    pub(super) fn draw_version_history(&mut self, ui: &mut Ui) {
+        let mut history: Vec<_> = self
+            .all_dlls
+            .iter()
+            .filter(|dll| matches!(dll.kind, OriDllKind::Rando(_)))
+            .cloned()
+            .collect();
+        if history.is_empty() {
+            return;
+        }
+        history.sort_by_key(|dll| Reverse(dll.kind));
+
+        CollapsingHeader::new(tr("version_history")).show(ui, |ui| {
+            ScrollArea::vertical().max_height(100.).show(ui, |ui| {
+                for dll in history {
+                    let OriDllKind::Rando(version) = dll.kind else {
+                        continue;
+                    };
+                    let is_current = self
+                        .current_dll
+                        .as_ref()
+                        .is_some_and(|current| current.kind == dll.kind);
+                    let mut hover = dll.path.to_string_lossy().into_owned();
+                    if let Some(comparison) = self.version_comparison(dll.kind) {
+                        hover = format!("{hover}\n{comparison}");
+                    }
+
+                    ui.horizontal(|ui| {
+                        let label = ui
+                            .selectable_label(is_current, format!("v{version}"))
+                            .on_hover_text(hover);
+                        label.context_menu(|ui| {
+                            if ui.button(tr("export_dll")).clicked() {
+                                ui.close_menu();
+                                self.export_dll(&dll);
+                            }
+                        });
+                        let clicked = label.clicked();
+                        if let Some(date) = dll.modified.and_then(format_date) {
+                            ui.weak(date);
+                        }
+
+                        if clicked && !is_current {
+                            self.switch_to_version(dll.clone());
+                        }
+                    });
+                }
+            });
+        });
+    }
+}
+
+/// Formats the date of `time` in UTC as `YYYY-MM-DD`.
+pub(super) fn format_date(time: SystemTime) -> Option<String> {
+    let days = time.duration_since(UNIX_EPOCH).ok()?.as_secs() / 86_400;
+    let days = i64::try_from(days).ok()?;
+
+    // Civil date from days since the epoch, see https://howardhinnant.github.io/date_algorithms.html
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    Some(format!("{year:04}-{month:02}-{day:02}"))
+}
@@ -0,0 +1,63 @@
+use crate::gui::Inner;
+use crate::lang::tr;
+use crate::managed_backup::backup_managed_dir;
+use eframe::egui::Ui;
+use rfd::FileDialog;
+use tracing::{error, info, instrument, warn};
+
+impl Inner {
+    pub(super) fn draw_full_backup_button(&mut self, ui: &mut Ui) {
+        if ui
+            .button(tr("create_full_backup"))
+            .on_hover_text(tr("create_full_backup_hover"))
+            .clicked()
+        {
+            self.create_full_backup();
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn create_full_backup(&mut self) {
+        if let Some(modal_message) = &self.modal_message {
+            warn!(
+                ?modal_message,
+                "Some modal action is already in progress, doing nothing"
+            );
+            return;
+        }
+
+        let Some(target) = FileDialog::new()
+            .set_file_name("Managed backup.zip")
+            .add_filter("Zip", &["zip"])
+            .save_file()
+        else {
+            return;
+        };
+
+        info!(?target, "Creating full backup");
+        self.modal_message = Some(tr("creating_full_backup").to_owned());
+
+        let game_dir = self.settings.game_dir.clone();
+        let weak_self = self.weak_self.clone();
+        self.run_off_thread(
+            move || {
+                backup_managed_dir(&game_dir, &target, |done, total| {
+                    let Some(app) = weak_self.upgrade() else {
+                        return;
+                    };
+                    let mut app = app.lock().unwrap();
+                    app.modal_message =
+                        Some(format!("{} ({done}/{total})", tr("creating_full_backup")));
+                    app.egui_ctx.request_repaint();
+                })
+            },
+            |app, result| {
+                app.modal_message = None;
+                if let Err(err) = result {
+                    error!(?err, "Couldn't create full backup");
+                    app.error_message = Some(format!("{}\n{err}", tr("failed_full_backup")));
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::gui::Inner;
+use crate::lang::tr;
+use eframe::egui::{RichText, Ui};
+use std::sync::{Mutex, Weak};
+use tracing::debug;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(super) enum StepStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// One entry of the checklist shown below the modal message.
+#[derive(Debug, Clone)]
+pub(super) struct ModalStep {
+    name: &'static str,
+    status: StepStatus,
+}
+
+/// Updates the checklist of the modal message while the work runs off thread.
+#[derive(Clone)]
+pub(super) struct StepProgress {
+    app: Weak<Mutex<Inner>>,
+}
+
+impl StepProgress {
+    pub(super) fn set(&self, step: usize, status: StepStatus) {
+        let Some(app) = self.app.upgrade() else {
+            return;
+        };
+        let mut app = app.lock().unwrap();
+        if let Some(step) = app.modal_steps.get_mut(step) {
+            debug!(name = step.name, ?status, "Modal step changed");
+            step.status = status;
+        }
+        app.egui_ctx.request_repaint();
+    }
+
+    /// Runs `f` as `step`, which is running meanwhile and done or failed afterwards.
+    pub(super) fn run<T, E>(&self, step: usize, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        self.set(step, StepStatus::Running);
+        let result = f();
+        self.set(
+            step,
+            if result.is_ok() {
+                StepStatus::Done
+            } else {
+                StepStatus::Failed
+            },
+        );
+        result
+    }
+}
+
+impl Inner {
+    /// Shows the translation keys `steps` as a checklist below the modal message, all pending at first.
+    /// The checklist goes away together with the modal message.
+    pub(super) fn start_modal_steps(&mut self, steps: &[&'static str]) -> StepProgress {
+        self.modal_steps = steps
+            .iter()
+            .map(|&name| ModalStep {
+                name,
+                status: StepStatus::Pending,
+            })
+            .collect();
+        StepProgress {
+            app: self.weak_self.clone(),
+        }
+    }
+
+    pub(super) fn draw_modal_steps(&self, ui: &mut Ui) {
+        for step in &self.modal_steps {
+            ui.horizontal(|ui| {
+                match step.status {
+                    StepStatus::Pending => {
+                        ui.weak("○");
+                    }
+                    StepStatus::Running => {
+                        ui.spinner();
+                    }
+                    StepStatus::Done => {
+                        ui.colored_label(self.success_color(), "✔");
+                    }
+                    StepStatus::Failed => {
+                        ui.colored_label(self.error_color(), "✖");
+                    }
+                }
+
+                let name = RichText::new(tr(step.name));
+                ui.label(if step.status == StepStatus::Pending {
+                    name.weak()
+                } else {
+                    name
+                });
+            });
+        }
+    }
+}
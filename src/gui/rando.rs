@@ -1,29 +1,71 @@
-use crate::dll_management::{OriDll, OriDllKind, install_dll};
-use crate::gui::{Inner, open_file_button};
-use eframe::egui::{ComboBox, Ui};
-use tracing::{error, info, instrument, warn};
+use crate::dll_classifier::RandoVersion;
+use crate::dll_management::{
+    InstallPlan, OriDll, OriDllKind, check_disk_space, install_dll, prune_backups, search_game_dir,
+};
+use crate::gui::{AppModal, Inner, NewestState, install_error_message, open_file_button};
+use crate::lang::tr;
+use eframe::egui::{Align, Button, ComboBox, Layout, Ui};
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, instrument, warn};
+
+const DEFENDER_EXCLUSION_URL: &str = "https://support.microsoft.com/en-us/windows/add-an-exclusion-to-windows-security-811816c0-4dfd-af4a-47e4-c301afe13b26";
+/// If the active dll disappears this soon after installing, antivirus software most likely removed it.
+const QUARANTINE_WINDOW: Duration = Duration::from_secs(10 * 60);
 
 impl Inner {
     #[instrument(skip_all)]
     pub(super) fn draw_rando_ui(&mut self, ui: &mut Ui) {
-        ui.separator();
+        let compact = self.settings.compact_mode;
+
+        if !compact {
+            ui.separator();
+        }
         self.draw_version_selector(ui);
+        self.draw_fingerprint(ui);
+        self.draw_stray_dll_warning(ui);
+        if compact {
+            return;
+        }
+
+        self.draw_version_history(ui);
         ui.separator();
         self.draw_open_directories(ui);
+
+        if self.settings.developer_mode {
+            self.draw_developer_info(ui);
+        }
     }
 
     #[instrument(skip_all)]
     fn draw_version_selector(&mut self, ui: &mut Ui) {
+        // Without any dlls the combo box would only show <None> with nothing to pick
+        if self.all_dlls.is_empty() {
+            ui.weak(tr("no_versions_yet"));
+            return;
+        }
+
         ui.horizontal(|ui| {
-            ui.label("Switch version");
+            ui.label(tr("switch_version"));
 
             ComboBox::from_id_salt("Select version CB")
                 .selected_text(format_dll(self.current_dll.as_ref()))
                 .show_ui(ui, |ui| {
+                    // The current dll might be filtered out, in which case nothing is selected.
+                    // Any selection is then a different version than the current one.
                     let mut new_version = self.current_dll.clone();
-                    for dll in self.all_dlls.iter().cloned().map(Some) {
-                        let label = format_dll(dll.as_ref());
-                        ui.selectable_value(&mut new_version, dll, label);
+                    let rando_only = self.settings.rando_only_versions;
+                    let visible_dlls = self
+                        .all_dlls
+                        .iter()
+                        .filter(|dll| !rando_only || dll.kind != OriDllKind::Vanilla);
+                    for dll in visible_dlls.cloned() {
+                        let label = format_dll(Some(&dll));
+                        let comparison = self.version_comparison(dll.kind);
+                        let response = ui.selectable_value(&mut new_version, Some(dll), label);
+                        if let Some(comparison) = comparison {
+                            response.on_hover_text(comparison);
+                        }
                     }
 
                     if different_version(new_version.as_ref(), self.current_dll.as_ref()) {
@@ -35,19 +77,175 @@ impl Inner {
                     }
                 });
         });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.settings.rando_only_versions, tr("rando_only"));
+            self.draw_restore_vanilla_button(ui);
+            self.draw_reinstall_button(ui);
+            self.draw_export_button(ui);
+            self.draw_download_only_button(ui);
+        });
+    }
+
+    fn draw_restore_vanilla_button(&mut self, ui: &mut Ui) {
+        let current_is_vanilla = self
+            .current_dll
+            .as_ref()
+            .is_some_and(|dll| dll.kind == OriDllKind::Vanilla);
+        let vanilla = self
+            .all_dlls
+            .iter()
+            .find(|dll| dll.kind == OriDllKind::Vanilla)
+            .cloned();
+
+        let Some(vanilla) = vanilla else {
+            return;
+        };
+
+        if ui
+            .add_enabled(!current_is_vanilla, Button::new(tr("restore_vanilla")))
+            .clicked()
+        {
+            self.switch_to_version(vanilla);
+        }
+    }
+
+    /// Installs the current version again, e.g. if the active dll got corrupted.
+    /// The newest version is downloaded again, other versions are restored from their backup.
+    fn draw_reinstall_button(&mut self, ui: &mut Ui) {
+        let Some(current) = &self.current_dll else {
+            return;
+        };
+
+        let is_newest = match (current.kind, &self.newest_version_available) {
+            (OriDllKind::Rando(installed), NewestState::Version(newest)) => installed == *newest,
+            _ => false,
+        };
+        // Duplicates are filtered in favor of copies other than the active dll
+        let backup = self
+            .all_dlls
+            .iter()
+            .find(|dll| dll.kind == current.kind && dll.path != current.path)
+            .cloned();
+
+        if !is_newest && backup.is_none() {
+            return;
+        }
+
+        if ui
+            .button(tr("reinstall"))
+            .on_hover_text(tr("reinstall_hover"))
+            .clicked()
+        {
+            info!(is_newest, ?backup, "Reinstalling current version");
+            if is_newest {
+                self.download_update();
+            } else if let Some(backup) = backup {
+                self.switch_to_version(backup);
+            }
+        }
+    }
+
+    fn draw_stray_dll_warning(&self, ui: &mut Ui) {
+        if self.stray_dlls.is_empty() {
+            return;
+        }
+
+        ui.colored_label(ui.visuals().warn_fg_color, tr("stray_dlls"));
+        for path in &self.stray_dlls {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            ui.monospace(name).on_hover_text(path.to_string_lossy());
+        }
     }
 
     #[instrument(skip_all)]
     fn draw_open_directories(&self, ui: &mut Ui) {
-        open_file_button(ui, "Open seed folder", || {
+        open_file_button(ui, tr("open_seed_folder"), || {
             self.settings.game_dir.install.clone()
         });
     }
 }
 
 impl Inner {
+    /// Antivirus software, mostly Windows Defender, sometimes quarantines the unsigned rando dll right after installing.
+    /// That looks like any missing dll, so users get told about the likely cause and how to prevent it.
+    #[instrument(skip(self))]
+    pub(super) fn check_quarantined_dll(&mut self) {
+        if self.current_dll.is_some() {
+            return;
+        }
+        let Some(last_install) = self.last_install.take() else {
+            return;
+        };
+        let elapsed = last_install.elapsed();
+        if elapsed > QUARANTINE_WINDOW {
+            return;
+        }
+
+        warn!(
+            ?elapsed,
+            "Active dll disappeared shortly after installing, likely quarantined by antivirus"
+        );
+
+        self.show_modal_ui(AppModal::new().dismissable(true), |_app, ui, modal| {
+            ui.label(tr("dll_quarantined"));
+            ui.hyperlink_to(tr("add_antivirus_exclusion"), DEFENDER_EXCLUSION_URL);
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("okay")).clicked() {
+                    modal.close();
+                }
+            });
+        });
+    }
+
+    /// Offers to restore a backup if there are backups but no active dll,
+    /// e.g. because a previous install failed after the active dll was backed up.
+    #[instrument(skip(self))]
+    pub(super) fn check_partial_install(&mut self) {
+        if self.current_dll.is_some() || self.restore_offered {
+            return;
+        }
+
+        let newest_known = self
+            .all_dlls
+            .iter()
+            .filter(|dll| matches!(dll.kind, OriDllKind::Rando(_)))
+            .max_by_key(|dll| dll.kind);
+        let Some(to_restore) = newest_known.or(self.all_dlls.last()).cloned() else {
+            return;
+        };
+
+        warn!(
+            ?to_restore,
+            "No active dll, but backups exist. Offering restore"
+        );
+        self.restore_offered = true;
+
+        self.show_modal_ui(AppModal::new(), move |app, ui, modal| {
+            ui.label(tr("missing_active_dll"));
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("not_now")).clicked() {
+                    modal.close();
+                }
+                if ui
+                    .button(format!(
+                        "{} {}",
+                        tr("restore"),
+                        format_dll(Some(&to_restore))
+                    ))
+                    .clicked()
+                {
+                    modal.close();
+                    app.switch_to_version(to_restore.clone());
+                }
+            });
+        });
+    }
+
     #[instrument(skip(self, version))]
-    fn switch_to_version(&mut self, version: OriDll) {
+    pub(super) fn switch_to_version(&mut self, version: OriDll) {
         if let Some(modal_message) = &self.modal_message {
             warn!(
                 ?modal_message,
@@ -57,38 +255,146 @@ impl Inner {
         }
 
         info!(to_install=?version, "Switching version");
-        self.modal_message = Some("Switching version...".to_owned());
+        self.modal_message = Some(tr("switching_version").to_owned());
 
         let game_dir = self.settings.game_dir.clone();
         let all_dlls = self.all_dlls.clone();
+        let max_backups = self.settings.max_backups;
+        let backup_location = self.settings.backup_location;
 
         self.run_off_thread(
-            move || {
-                if let Err(err) = install_dll(&game_dir, &version, &all_dlls) {
-                    error!(?version, ?err, "Couldn't install new dll");
-                    true
-                } else {
-                    false
+            move || -> color_eyre::Result<InstallPlan> {
+                let dll_size = std::fs::metadata(&version.path).map_or(0, |m| m.len());
+                check_disk_space(&game_dir, dll_size)?;
+                let plan = install_dll(&game_dir, &version, &all_dlls, backup_location)?;
+                if let Err(err) = prune_backups(&game_dir, max_backups) {
+                    warn!(?err, "Couldn't prune old backups");
                 }
+                Ok(plan)
             },
-            |app, errored| {
+            |app, result| {
                 app.modal_message = None;
                 app.update_dlls();
-                if errored {
-                    app.error_message = Some("Failed to switch version".into());
+                match result {
+                    Ok(plan) => {
+                        app.last_install = Some(Instant::now());
+                        app.notify_vanilla_backup(&plan);
+                        if app.settings.launch_after_switch {
+                            info!("Launching game after switching version");
+                            app.settings.game_dir.launch_game(app.settings.launch_type);
+                        }
+                    }
+                    Err(err) => {
+                        error!(?err, "Couldn't install new dll");
+                        app.error_message =
+                            Some(install_error_message(tr("failed_switch_version"), &err));
+                    }
                 }
             },
         );
     }
+
+    /// Swaps vanilla back in while closing the app. Only runs on a clean exit,
+    /// when quitting during an install the dll is left as it is.
+    #[instrument(skip_all)]
+    pub(super) fn restore_vanilla_on_exit(&self) {
+        if let Some(modal_message) = &self.modal_message {
+            warn!(
+                ?modal_message,
+                "Operation in progress, not restoring vanilla on exit"
+            );
+            return;
+        }
+
+        let game_dir = &self.settings.game_dir;
+        let (current, all_dlls, _stray) = match search_game_dir(game_dir) {
+            Ok(dlls) => dlls,
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Couldn't search game dir, not restoring vanilla on exit"
+                );
+                return;
+            }
+        };
+        if current.is_some_and(|dll| dll.kind == OriDllKind::Vanilla) {
+            debug!("Vanilla is already active");
+            return;
+        }
+        let Some(vanilla) = all_dlls.iter().find(|dll| dll.kind == OriDllKind::Vanilla) else {
+            info!("No vanilla backup available, not restoring vanilla on exit");
+            return;
+        };
+
+        info!(?vanilla.path, "Restoring vanilla on exit");
+        if let Err(err) = install_dll(game_dir, vanilla, &all_dlls, self.settings.backup_location) {
+            error!(?err, "Couldn't restore vanilla on exit");
+        }
+    }
+
+    /// How a rando version relates to the active one and to the latest release, for telling backups apart.
+    /// Vanilla isn't compared to anything.
+    pub(super) fn version_comparison(&self, kind: OriDllKind) -> Option<String> {
+        let version = match kind {
+            OriDllKind::Vanilla => return None,
+            OriDllKind::Rando(version) => Some(version),
+            OriDllKind::UnknownRando(_) => None,
+        };
+        let active = match self.current_dll.as_ref().map(|dll| dll.kind) {
+            Some(OriDllKind::Rando(active)) => Some(active),
+            _ => None,
+        };
+        let latest = match self.newest_version_available {
+            NewestState::Version(latest) => Some(latest),
+            _ => None,
+        };
+
+        let compare = |other: Option<RandoVersion>| match version.zip(other) {
+            Some((version, other)) => match version.cmp(&other) {
+                Ordering::Less => tr("compare_older"),
+                Ordering::Equal => tr("compare_same"),
+                Ordering::Greater => tr("compare_newer"),
+            },
+            None => tr("compare_unavailable"),
+        };
+        Some(format!(
+            "{} {}\n{} {}",
+            tr("compare_active"),
+            compare(active),
+            tr("compare_latest"),
+            compare(latest),
+        ))
+    }
+
+    /// Lets the user know once a vanilla backup was created, so they know they can always go back.
+    pub(super) fn notify_vanilla_backup(&mut self, plan: &InstallPlan) {
+        let Some(backup) = plan.vanilla_backup() else {
+            return;
+        };
+
+        info!(?backup, "Created vanilla backup");
+        let backup = backup.to_string_lossy().into_owned();
+
+        self.show_modal_ui(AppModal::new().dismissable(true), move |_app, ui, modal| {
+            ui.label(tr("vanilla_backup_created"));
+            ui.label(&backup);
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("okay")).clicked() {
+                    modal.close();
+                }
+            });
+        });
+    }
 }
 
 fn format_dll(dll: Option<&OriDll>) -> String {
     match dll {
-        None => "<None>".to_owned(),
+        None => tr("none").to_owned(),
         Some(dll) => match dll.kind {
-            OriDllKind::Vanilla => "Vanilla".to_owned(),
-            OriDllKind::Rando(v) => format!("Rando v{v}"),
-            OriDllKind::UnknownRando(_) => format!("Rando [{}]", dll.display_name),
+            OriDllKind::Vanilla => tr("vanilla").to_owned(),
+            OriDllKind::Rando(v) => format!("{} v{v}", tr("rando")),
+            OriDllKind::UnknownRando(_) => format!("{} [{}]", tr("rando"), dll.display_name),
         },
     }
 }
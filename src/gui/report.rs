@@ -0,0 +1,78 @@
+use crate::gui::Inner;
+use crate::lang::tr;
+use crate::logging::existing_log_file;
+use eframe::egui::Ui;
+use reqwest::Url;
+use std::fmt::Write;
+use tracing::{error, info, instrument};
+
+const NEW_ISSUE_URL: &str = "https://github.com/ori-community/bf-rando-installer/issues/new";
+
+impl Inner {
+    /// Opens a new github issue, prefilled with diagnostics and `error`, if any.
+    pub(super) fn draw_report_problem_button(&self, ui: &mut Ui, error: Option<&str>) {
+        if ui.button(tr("report_problem")).clicked() {
+            self.open_problem_report(error);
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn open_problem_report(&self, error: Option<&str>) {
+        let body = self.diagnostics(error);
+        let url = match Url::parse_with_params(NEW_ISSUE_URL, [("body", body)]) {
+            Ok(url) => url,
+            Err(err) => {
+                error!(?err, "Couldn't build issue url");
+                return;
+            }
+        };
+
+        info!("Opening problem report");
+        if let Err(err) = opener::open(url.as_str()) {
+            error!(?err, "Couldn't open problem report");
+        }
+    }
+
+    fn diagnostics(&self, error: Option<&str>) -> String {
+        let mut body =
+            String::from("<!-- Please describe the problem here -->\n\n\n### Diagnostics\n");
+
+        // Writing to a String can't fail
+        _ = writeln!(body, "- App version: {}", env!("CARGO_PKG_VERSION"));
+        _ = writeln!(
+            body,
+            "- OS: {} {}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        _ = writeln!(
+            body,
+            "- Game dir: {}",
+            self.settings.game_dir.install.display()
+        );
+        let active = self
+            .current_dll
+            .as_ref()
+            .map_or_else(|| "<none>".to_owned(), ToString::to_string);
+        _ = writeln!(body, "- Active version: {active}");
+        let installed: Vec<_> = self.all_dlls.iter().map(ToString::to_string).collect();
+        _ = writeln!(body, "- Installed versions: {}", installed.join(", "));
+        _ = writeln!(
+            body,
+            "- Available version: {:?}",
+            self.newest_version_available
+        );
+        if let Some(error) = error {
+            _ = writeln!(body, "- Error: {error}");
+        }
+        if let Some(log_file) = existing_log_file() {
+            _ = writeln!(
+                body,
+                "\nPlease attach the log file, found at `{}`",
+                log_file.display()
+            );
+        }
+
+        body
+    }
+}
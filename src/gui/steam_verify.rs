@@ -0,0 +1,111 @@
+use crate::dll_management::{OriDllKind, plan_uninstall, uninstall};
+use crate::game::open_steam_verification;
+use crate::gui::{AppModal, Inner};
+use crate::lang::tr;
+use eframe::egui::{Align, Layout, Ui};
+use tracing::{error, info, instrument, warn};
+
+impl Inner {
+    /// Steam's file verification replaces the randomizer with vanilla, which looks like the randomizer uninstalled itself.
+    /// This walks through restoring vanilla first, verifying, and reinstalling the randomizer afterwards.
+    pub(super) fn draw_steam_verify_button(&mut self, ui: &mut Ui) {
+        if ui
+            .button(tr("steam_verify"))
+            .on_hover_text(tr("steam_verify_hover"))
+            .clicked()
+        {
+            self.show_modal_ui(AppModal::new().dismissable(true), |app, ui, modal| {
+                ui.heading(tr("steam_verify"));
+                ui.label(tr("steam_verify_explanation"));
+
+                ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                    if ui.button(tr("cancel")).clicked() {
+                        modal.close();
+                    }
+                    if ui.button(tr("steam_verify_restore_vanilla")).clicked() {
+                        modal.close();
+                        app.restore_vanilla_for_verification();
+                    }
+                });
+            });
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn restore_vanilla_for_verification(&mut self) {
+        if let Some(modal_message) = &self.modal_message {
+            warn!(
+                ?modal_message,
+                "Some modal action is already in progress, doing nothing"
+            );
+            return;
+        }
+
+        // The active dll gets a different path when it's backed up, so it's found again by its kind
+        let previous = self.current_dll.as_ref().map(|dll| dll.kind);
+        info!(?previous, "Restoring vanilla for steam verification");
+        self.modal_message = Some(tr("restoring_vanilla").to_owned());
+        let progress = self.start_modal_steps(&["step_find_vanilla", "step_restore_vanilla"]);
+
+        let game_dir = self.settings.game_dir.clone();
+        let backup_location = self.settings.backup_location;
+        self.run_off_thread(
+            move || -> color_eyre::Result<()> {
+                let plan = progress.run(0, || plan_uninstall(&game_dir))?;
+                progress.run(1, || uninstall(&game_dir, &plan, false, backup_location))
+            },
+            move |app, result| {
+                app.modal_message = None;
+                app.update_dlls();
+                match result {
+                    Ok(()) => app.show_steam_verify_modal(previous),
+                    Err(err) => {
+                        error!(?err, "Couldn't restore vanilla for steam verification");
+                        app.error_message =
+                            Some(format!("{}\n{err}", tr("failed_restore_vanilla")));
+                    }
+                }
+            },
+        );
+    }
+
+    fn show_steam_verify_modal(&mut self, previous: Option<OriDllKind>) {
+        self.show_modal_ui(AppModal::new(), move |app, ui, modal| {
+            ui.heading(tr("steam_verify"));
+            ui.label(tr("steam_verify_now"));
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("steam_verify_done")).clicked() {
+                    modal.close();
+                    app.reinstall_after_verification(previous);
+                }
+                if ui.button(tr("steam_verify_open")).clicked() {
+                    match open_steam_verification() {
+                        Ok(()) => info!("Opened steam verification"),
+                        Err(err) => {
+                            error!(?err, "Couldn't open steam verification");
+                            app.error_message = Some(tr("failed_open_steam_verification").into());
+                        }
+                    }
+                }
+                if ui.button(tr("steam_verify_keep_vanilla")).clicked() {
+                    modal.close();
+                }
+            });
+        });
+    }
+
+    /// Reinstalls the previously active version if there's still a copy of it, otherwise the newest version.
+    #[instrument(skip(self))]
+    fn reinstall_after_verification(&mut self, previous: Option<OriDllKind>) {
+        let backup = previous
+            .filter(|kind| *kind != OriDllKind::Vanilla)
+            .and_then(|kind| self.all_dlls.iter().find(|dll| dll.kind == kind))
+            .cloned();
+
+        match backup {
+            Some(backup) => self.switch_to_version(backup),
+            None => self.download_update(),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::gui::{App, Inner};
+use crate::lang::tr;
+use color_eyre::Result;
+use color_eyre::eyre::{OptionExt, WrapErr};
+use eframe::egui::{Context, IconData, ViewportCommand};
+use std::sync::{Mutex, Weak};
+use tracing::{error, info, instrument, warn};
+use tray_icon::menu::{Menu, MenuEvent, MenuItem};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+// Menu item ids are fixed, as the menu event handler can only be set once
+const OPEN_ID: &str = "open";
+const LAUNCH_ID: &str = "launch";
+const QUIT_ID: &str = "quit";
+
+#[instrument(skip_all)]
+fn create_tray_icon(icon: Option<&IconData>) -> Result<TrayIcon> {
+    // Without an icon, the hidden window couldn't be found in the tray anymore
+    let icon = icon.ok_or_eyre("No icon for the tray")?;
+
+    let menu = Menu::new();
+    menu.append_items(&[
+        &MenuItem::with_id(OPEN_ID, tr("tray_open"), true, None),
+        &MenuItem::with_id(LAUNCH_ID, tr("launch_game"), true, None),
+        &MenuItem::with_id(QUIT_ID, tr("tray_quit"), true, None),
+    ])
+    .wrap_err("Error creating tray menu")?;
+
+    let icon = Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height)
+        .wrap_err("Error creating tray icon image")?;
+
+    TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("Ori DE Randomizer")
+        .with_icon(icon)
+        .build()
+        .wrap_err("Error creating tray icon")
+}
+
+pub(super) fn set_menu_event_handler(inner: Weak<Mutex<Inner>>) {
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        // The handler runs inside the windows message loop,
+        // which can also be entered while the app is locked (e.g. by a file dialog)
+        let Ok(mut app) = inner.try_lock() else {
+            warn!(?event, "App is busy, ignoring tray menu event");
+            return;
+        };
+
+        app.handle_tray_event(&event);
+    }));
+}
+
+impl Inner {
+    #[instrument(skip(self))]
+    fn handle_tray_event(&mut self, event: &MenuEvent) {
+        if event.id == OPEN_ID {
+            self.restore_from_tray = true;
+            self.egui_ctx
+                .send_viewport_cmd(ViewportCommand::Visible(true));
+            self.egui_ctx
+                .send_viewport_cmd(ViewportCommand::Minimized(false));
+            self.egui_ctx.send_viewport_cmd(ViewportCommand::Focus);
+        } else if event.id == LAUNCH_ID {
+            self.settings
+                .game_dir
+                .launch_game(self.settings.launch_type);
+        } else if event.id == QUIT_ID {
+            self.egui_ctx.send_viewport_cmd(ViewportCommand::Close);
+        }
+
+        self.egui_ctx.request_repaint();
+    }
+}
+
+impl App {
+    /// Hides the window into the tray when it gets minimized, if enabled in the settings.
+    /// The tray icon is removed again when the window gets restored.
+    pub(super) fn update_tray(&mut self, app: &mut Inner, ctx: &Context) {
+        if app.restore_from_tray {
+            app.restore_from_tray = false;
+            info!("Restoring window from tray");
+            self.tray = None;
+            return;
+        }
+
+        let minimized = ctx.input(|i| i.viewport().minimized == Some(true));
+        if self.tray.is_none() && minimized && app.settings.minimize_to_tray {
+            match create_tray_icon(self.icon.as_ref()) {
+                Ok(tray) => {
+                    info!("Minimizing to tray");
+                    self.tray = Some(tray);
+                    ctx.send_viewport_cmd(ViewportCommand::Visible(false));
+                }
+                Err(err) => error!(?err, "Couldn't minimize to tray"),
+            }
+        }
+    }
+}
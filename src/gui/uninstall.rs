@@ -0,0 +1,100 @@
+use crate::dll_management::{UninstallPlan, plan_uninstall, uninstall};
+use crate::gui::{AppModal, Inner, install_error_message};
+use crate::lang::tr;
+use eframe::egui::{Align, Layout, ScrollArea, Ui};
+use tracing::{error, info, instrument, warn};
+
+impl Inner {
+    pub(super) fn draw_uninstall_button(&mut self, ui: &mut Ui) {
+        if ui.button(tr("uninstall")).clicked() {
+            self.prepare_uninstall();
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn prepare_uninstall(&mut self) {
+        if let Some(modal_message) = &self.modal_message {
+            warn!(
+                ?modal_message,
+                "Some modal action is already in progress, doing nothing"
+            );
+            return;
+        }
+
+        self.modal_message = Some(tr("preparing_uninstall").to_owned());
+
+        let game_dir = self.settings.game_dir.clone();
+        self.run_off_thread(
+            move || plan_uninstall(&game_dir),
+            |app, plan| {
+                app.modal_message = None;
+                match plan {
+                    Ok(plan) => app.show_uninstall_modal(plan),
+                    Err(err) => {
+                        error!(?err, "Couldn't prepare uninstall");
+                        app.error_message =
+                            Some(format!("{}\n{err}", tr("failed_prepare_uninstall")));
+                    }
+                }
+            },
+        );
+    }
+
+    fn show_uninstall_modal(&mut self, plan: UninstallPlan) {
+        info!(?plan, "Asking for uninstall confirmation");
+
+        self.show_modal_ui(AppModal::new().dismissable(true), move |app, ui, modal| {
+            ui.heading(tr("uninstall"));
+            ui.label(tr("uninstall_restores_vanilla"));
+
+            if plan.rando_files.is_empty() {
+                ui.label(tr("uninstall_no_backups"));
+            } else {
+                ui.label(tr("uninstall_deleted_files"));
+                ScrollArea::vertical().max_height(100.).show(ui, |ui| {
+                    for path in &plan.rando_files {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        ui.monospace(name).on_hover_text(path.to_string_lossy());
+                    }
+                });
+            }
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("cancel")).clicked() {
+                    modal.close();
+                }
+                if ui.button(tr("uninstall_keep_backups")).clicked() {
+                    modal.close();
+                    app.uninstall(plan.clone(), false);
+                }
+                if !plan.rando_files.is_empty()
+                    && ui.button(tr("uninstall_delete_backups")).clicked()
+                {
+                    modal.close();
+                    app.uninstall(plan.clone(), true);
+                }
+            });
+        });
+    }
+
+    #[instrument(skip(self, plan))]
+    fn uninstall(&mut self, plan: UninstallPlan, delete_backups: bool) {
+        self.modal_message = Some(tr("uninstalling").to_owned());
+
+        let game_dir = self.settings.game_dir.clone();
+        let backup_location = self.settings.backup_location;
+        self.run_off_thread(
+            move || {
+                uninstall(&game_dir, &plan, delete_backups, backup_location)
+                    .inspect_err(|err| error!(?err, "Couldn't uninstall"))
+            },
+            |app, result| {
+                app.modal_message = None;
+                app.update_dlls();
+                if let Err(err) = result {
+                    app.error_message = Some(install_error_message(tr("failed_uninstall"), &err));
+                }
+            },
+        );
+    }
+}
@@ -1,31 +1,165 @@
 use crate::dll_classifier::RandoVersion;
-use crate::dll_management::install_new_dll;
-use crate::gui::{Inner, InstalledState, NewestState};
+use crate::dll_management::{
+    InstallError, InstallPlan, check_disk_space, install_new_dll, largest_dll_size, prune_backups,
+};
+use crate::gui::history::format_date;
+use crate::gui::{AppModal, Inner, InstalledState, NewestState, install_error_message};
+use crate::lang::{tr, tr_count};
 use crate::orirando::download_dll;
-use eframe::egui::{Align, Color32, FontFamily, FontId, Layout, Spinner, TextStyle, Ui, Widget};
+use color_eyre::Report;
+use eframe::egui::{
+    Align, FontFamily, FontId, Layout, RichText, Spinner, TextStyle, Ui, Widget, WidgetInfo,
+    WidgetType,
+};
 use egui_alignments::Aligner;
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
 use tracing::{error, info, instrument, warn};
 
+/// The installed and available versions summarized into one status.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SyncStatus {
+    Checking,
+    NotInstalled,
+    UpToDate,
+    UpdateAvailable(RandoVersion),
+    Pinned(RandoVersion),
+    /// Installed builds newer than the site's are usually betas.
+    NewerThanServer,
+    CustomBuild,
+    ServerUnreachable,
+}
+
+/// `None` if there's nothing worth summarizing, e.g. before loading or for unsupported game dirs.
+fn sync_status(
+    installed: &InstalledState,
+    available: &NewestState,
+    pinned: Option<RandoVersion>,
+) -> Option<SyncStatus> {
+    let installed = match installed {
+        InstalledState::Unknown | InstalledState::OriginalGame | InstalledState::MissingDll => {
+            return None;
+        }
+        InstalledState::Checking => return Some(SyncStatus::Checking),
+        InstalledState::None => return Some(SyncStatus::NotInstalled),
+        InstalledState::InstalledUnknown { .. } => return Some(SyncStatus::CustomBuild),
+        InstalledState::Installed(installed) => *installed,
+    };
+
+    Some(match available {
+        NewestState::Unknown => return None,
+        NewestState::Checking => SyncStatus::Checking,
+        NewestState::Error | NewestState::SiteUnavailable => SyncStatus::ServerUnreachable,
+        NewestState::Version(newest) if installed == *newest => SyncStatus::UpToDate,
+        NewestState::Version(newest) if installed > *newest => SyncStatus::NewerThanServer,
+        NewestState::Version(_) if pinned == Some(installed) => SyncStatus::Pinned(installed),
+        NewestState::Version(newest) => SyncStatus::UpdateAvailable(*newest),
+    })
+}
+
 impl Inner {
+    /// A single line on top of the detailed version lines, to tell at a glance whether anything needs doing.
+    fn draw_sync_status(&self, ui: &mut Ui) {
+        let Some(status) = sync_status(
+            &self.newest_version_installed,
+            &self.newest_version_available,
+            self.settings.pinned_version,
+        ) else {
+            return;
+        };
+
+        let text = match status {
+            SyncStatus::Checking => tr("sync_checking").to_owned(),
+            SyncStatus::NotInstalled => tr("sync_not_installed").to_owned(),
+            SyncStatus::UpToDate => tr("sync_up_to_date").to_owned(),
+            SyncStatus::UpdateAvailable(newest) => {
+                format!("{} v{newest}", tr("sync_update_available"))
+            }
+            SyncStatus::Pinned(installed) => format!("{} v{installed}", tr("pinned_to")),
+            SyncStatus::NewerThanServer => tr("sync_newer_than_server").to_owned(),
+            SyncStatus::CustomBuild => tr("sync_custom_build").to_owned(),
+            SyncStatus::ServerUnreachable => tr("sync_server_unreachable").to_owned(),
+        };
+        let color = match status {
+            SyncStatus::UpToDate => self.success_color(),
+            SyncStatus::ServerUnreachable => self.error_color(),
+            SyncStatus::UpdateAvailable(_) => self.accent_color(),
+            _ => ui.visuals().strong_text_color(),
+        };
+
+        ui.label(RichText::new(text).size(16.).color(color));
+    }
+
     pub(super) fn draw_rando_version(&mut self, ui: &mut Ui) {
+        ui.vertical_centered(|ui| self.draw_sync_status(ui));
         ui.vertical_centered(|ui| match self.newest_version_installed {
             InstalledState::Unknown => {}
             InstalledState::Checking => {
-                ui.label("Loading installed versions...");
+                ui.label(tr("loading_versions"));
             }
             InstalledState::None => {
-                self.draw_install_button(ui, "Install Randomizer", true);
+                self.draw_install_button(ui, tr("install_randomizer"), true);
             }
-            InstalledState::InstalledUnknown => {
-                ui.label("✔ Rando installed");
+            InstalledState::InstalledUnknown { .. } => {
+                self.draw_custom_build_label(ui);
+                self.draw_replace_custom_build_button(ui);
             }
             InstalledState::Installed(installed) => {
-                ui.label(format!("✔ Rando installed ({installed})"));
+                Aligner::center_top()
+                    .layout(Layout::left_to_right(Align::Center))
+                    .show(ui, |ui| {
+                        ui.label(format!("{} ({installed})", tr("rando_installed")));
+                        self.draw_pin_toggle(ui, installed);
+                    });
                 self.draw_update_line(ui, installed);
             }
+            InstalledState::OriginalGame => {
+                ui.colored_label(ui.visuals().warn_fg_color, tr("original_game"));
+            }
+            InstalledState::MissingDll => {
+                ui.colored_label(ui.visuals().warn_fg_color, tr("missing_game_dll"));
+                self.draw_install_without_vanilla_button(ui);
+            }
         });
     }
 
+    /// Without a version to show, the file date at least tells custom builds apart.
+    fn draw_custom_build_label(&self, ui: &mut Ui) {
+        let InstalledState::InstalledUnknown {
+            display_name,
+            modified,
+        } = &self.newest_version_installed
+        else {
+            return;
+        };
+
+        let details = match (*modified).and_then(format_date) {
+            Some(date) => format!("{}, {date}", tr("custom_build")),
+            None => tr("custom_build").to_owned(),
+        };
+        ui.label(format!("{} ({details})", tr("rando_installed")))
+            .on_hover_text(display_name);
+    }
+
+    /// Without a vanilla dll there's nothing to back up, so uninstalling would need a file verification later.
+    fn draw_install_without_vanilla_button(&mut self, ui: &mut Ui) {
+        if ui.button(tr("install_anyway")).clicked() {
+            self.show_modal_ui(AppModal::new().dismissable(true), |app, ui, modal| {
+                ui.label(tr("install_without_vanilla_warning"));
+
+                ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                    if ui.button(tr("cancel")).clicked() {
+                        modal.close();
+                    }
+                    if ui.button(tr("install_anyway")).clicked() {
+                        modal.close();
+                        app.download_update();
+                    }
+                });
+            });
+        }
+    }
+
     fn draw_update_line(&mut self, ui: &mut Ui, installed: RandoVersion) {
         match self.newest_version_available {
             NewestState::Unknown => {}
@@ -33,23 +167,122 @@ impl Inner {
                 Aligner::center_top()
                     .layout(Layout::right_to_left(Align::Center))
                     .show(ui, |ui| {
-                        let resp = ui.label("Checking for updates...");
+                        let resp = ui.label(tr("checking_updates"));
                         Spinner::new().size(resp.rect.height()).ui(ui);
                     });
             }
             NewestState::Error => {
-                ui.colored_label(Color32::RED, "✖ Error checking for updates");
+                ui.colored_label(self.error_color(), tr("error_checking_updates"));
+            }
+            NewestState::SiteUnavailable => {
+                ui.colored_label(self.error_color(), tr("site_maintenance"));
             }
             NewestState::Version(newest) => {
-                if installed == newest {
-                    ui.colored_label(Color32::GREEN, "✔ Already on newest version");
-                } else {
-                    self.draw_install_button(ui, &format!("Update to v{newest}"), false);
+                match installed.cmp(&newest) {
+                    Ordering::Equal => {
+                        ui.colored_label(self.success_color(), tr("already_newest"));
+                    }
+                    // Usually a beta, "updating" would downgrade it
+                    Ordering::Greater => {
+                        ui.label(format!("{} (v{newest})", tr("sync_newer_than_server")));
+                    }
+                    Ordering::Less if self.settings.pinned_version == Some(installed) => {
+                        Aligner::center_top()
+                            .layout(Layout::left_to_right(Align::Center))
+                            .show(ui, |ui| {
+                                ui.label(format!("{} v{installed}", tr("pinned_to")));
+                                if ui.button(tr("unpin")).clicked() {
+                                    info!(?installed, "Unpinning version");
+                                    self.settings.pinned_version = None;
+                                }
+                            });
+                    }
+                    Ordering::Less => {
+                        self.draw_install_button(
+                            ui,
+                            &format!("{} v{newest}", tr("update_to")),
+                            false,
+                        );
+                        if let Some(date) = &self.newest_release_date {
+                            ui.weak(format!("{} {date}", tr("released")));
+                        }
+                    }
                 }
+                self.draw_last_version_check(ui);
             }
         }
     }
 
+    /// Pinning keeps deliberately fixed setups, e.g. for tournaments, from being updated by accident.
+    fn draw_pin_toggle(&mut self, ui: &mut Ui, installed: RandoVersion) {
+        let mut pinned = self.settings.pinned_version == Some(installed);
+        let hover_text = if pinned {
+            tr("unpin")
+        } else {
+            tr("pin_version")
+        };
+        let toggle = ui.toggle_value(&mut pinned, "📌").on_hover_text(hover_text);
+        toggle.widget_info(|| {
+            WidgetInfo::selected(WidgetType::SelectableLabel, true, pinned, tr("pin_version"))
+        });
+
+        if toggle.changed() {
+            info!(?installed, pinned, "Changing pinned version");
+            self.settings.pinned_version = pinned.then_some(installed);
+        }
+    }
+
+    fn draw_last_version_check(&self, ui: &mut Ui) {
+        let Some(elapsed) = self
+            .settings
+            .last_version_check
+            .and_then(|time| time.elapsed().ok())
+        else {
+            return;
+        };
+
+        let minutes = elapsed.as_secs() / 60;
+        let text = match minutes {
+            0 => tr("checked_just_now").to_owned(),
+            1..60 => tr_count("checked_minutes_ago", minutes),
+            60..1440 => tr_count("checked_hours_ago", minutes / 60),
+            _ => tr_count("checked_days_ago", minutes / 1440),
+        };
+        ui.weak(text);
+
+        // Keep the relative time current without repainting every frame
+        ui.ctx().request_repaint_after(Duration::from_secs(30));
+    }
+
+    /// Custom builds aren't recognized as a version, so replacing them with the public version has to be confirmed.
+    fn draw_replace_custom_build_button(&mut self, ui: &mut Ui) {
+        let NewestState::Version(newest) = self.newest_version_available else {
+            return;
+        };
+
+        if ui
+            .button(format!("{} v{newest}", tr("replace_custom_build")))
+            .clicked()
+        {
+            self.show_modal_ui(AppModal::new().dismissable(true), move |app, ui, modal| {
+                ui.label(tr("replace_custom_build_warning"));
+
+                ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                    if ui.button(tr("cancel")).clicked() {
+                        modal.close();
+                    }
+                    if ui
+                        .button(format!("{} v{newest}", tr("replace_custom_build")))
+                        .clicked()
+                    {
+                        modal.close();
+                        app.download_update();
+                    }
+                });
+            });
+        }
+    }
+
     fn draw_install_button(&mut self, ui: &mut Ui, text: &str, big: bool) {
         ui.scope(|ui| {
             ui.style_mut().text_styles.insert(
@@ -57,7 +290,7 @@ impl Inner {
                 FontId::new(if big { 20. } else { 13. }, FontFamily::Proportional),
             );
 
-            let color = self.theme_color(Color32::LIGHT_BLUE, Color32::from_rgb(77, 140, 156));
+            let color = self.accent_color();
 
             let style = ui.style_mut();
             let widgets = &mut style.visuals.widgets;
@@ -73,7 +306,7 @@ impl Inner {
 }
 impl Inner {
     #[instrument(skip(self))]
-    fn download_update(&mut self) {
+    pub(super) fn download_update(&mut self) {
         if let Some(modal_message) = &self.modal_message {
             warn!(
                 ?modal_message,
@@ -82,22 +315,54 @@ impl Inner {
             return;
         }
 
-        self.modal_message = Some("Installing Randomizer...".to_owned());
+        self.modal_message = Some(tr("installing_randomizer").to_owned());
+        let progress =
+            self.start_modal_steps(&["step_download", "step_install", "step_prune_backups"]);
 
         let game_dir = self.settings.game_dir.clone();
         let all_dlls = self.all_dlls.clone();
+        let mirrors = self.settings.dll_mirrors.clone();
+        let max_backups = self.settings.max_backups;
+        let backup_location = self.settings.backup_location;
 
         info!("Downloading update");
         self.run_off_thread(
-            move || -> color_eyre::Result<()> {
-                let dll = download_dll()?;
-                install_new_dll(&game_dir, &dll, &all_dlls)?;
-                Ok(())
+            move || -> Result<InstallPlan, (UpdateStep, Report)> {
+                let install_step = |err: InstallError| (UpdateStep::Install, Report::new(err));
+
+                let dll = progress.run(0, || -> Result<_, (UpdateStep, Report)> {
+                    // The new dll is most likely about as large as the ones already there
+                    check_disk_space(&game_dir, largest_dll_size(&all_dlls))
+                        .map_err(install_step)?;
+                    download_dll(&mirrors).map_err(|err| (UpdateStep::Download, Report::new(err)))
+                })?;
+                let plan = progress.run(1, || -> Result<_, (UpdateStep, Report)> {
+                    check_disk_space(&game_dir, dll.len() as u64).map_err(install_step)?;
+                    install_new_dll(&game_dir, &dll, &all_dlls, backup_location)
+                        .map_err(install_step)
+                })?;
+                // Old backups are only cleaned up, failing to do so doesn't fail the update
+                let pruned = progress.run(2, || prune_backups(&game_dir, max_backups));
+                if let Err(err) = pruned {
+                    warn!(?err, "Couldn't prune old backups");
+                }
+                Ok(plan)
             },
             |app, result| {
-                if let Err(err) = result {
-                    error!(?err, "Error downloading update");
-                    app.error_message = Some("Failed to ".into());
+                match result {
+                    Ok(plan) => {
+                        app.last_install = Some(Instant::now());
+                        app.notify_vanilla_backup(&plan);
+                        if app.settings.launch_after_install {
+                            info!("Launching game after install");
+                            app.settings.game_dir.launch_game(app.settings.launch_type);
+                        }
+                    }
+                    Err((step, err)) => {
+                        error!(?step, ?err, "Error downloading update");
+                        let message = install_error_message(step.failed_message(), &err);
+                        app.error_message = Some(format!("{message}\n{}", tr("see_log")));
+                    }
                 }
 
                 app.modal_message = None;
@@ -106,3 +371,156 @@ impl Inner {
         );
     }
 }
+
+/// Which part of an update failed, to tell connection problems apart from problems with the game dir.
+#[derive(Debug, Copy, Clone)]
+enum UpdateStep {
+    Download,
+    Install,
+}
+
+impl UpdateStep {
+    fn failed_message(self) -> &'static str {
+        match self {
+            UpdateStep::Download => tr("failed_download_update"),
+            UpdateStep::Install => tr("failed_install_update"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: RandoVersion = RandoVersion {
+        major: 4,
+        minor: 0,
+        patch: 1,
+    };
+    const NEW: RandoVersion = RandoVersion {
+        major: 4,
+        minor: 1,
+        patch: 0,
+    };
+
+    #[test]
+    fn nothing_to_summarize() {
+        for installed in [
+            InstalledState::Unknown,
+            InstalledState::OriginalGame,
+            InstalledState::MissingDll,
+        ] {
+            assert_eq!(
+                sync_status(&installed, &NewestState::Version(NEW), None),
+                None
+            );
+        }
+        assert_eq!(
+            sync_status(&InstalledState::Installed(OLD), &NewestState::Unknown, None),
+            None
+        );
+    }
+
+    #[test]
+    fn without_known_version() {
+        let available = NewestState::Version(NEW);
+        assert_eq!(
+            sync_status(&InstalledState::Checking, &available, None),
+            Some(SyncStatus::Checking)
+        );
+        assert_eq!(
+            sync_status(&InstalledState::None, &available, None),
+            Some(SyncStatus::NotInstalled)
+        );
+        let custom = InstalledState::InstalledUnknown {
+            display_name: "Assembly-CSharp.dll".to_owned(),
+            modified: None,
+        };
+        assert_eq!(
+            sync_status(&custom, &available, None),
+            Some(SyncStatus::CustomBuild)
+        );
+    }
+
+    #[test]
+    fn without_server_version() {
+        let installed = InstalledState::Installed(OLD);
+        assert_eq!(
+            sync_status(&installed, &NewestState::Checking, None),
+            Some(SyncStatus::Checking)
+        );
+        assert_eq!(
+            sync_status(&installed, &NewestState::Error, None),
+            Some(SyncStatus::ServerUnreachable)
+        );
+        assert_eq!(
+            sync_status(&installed, &NewestState::SiteUnavailable, None),
+            Some(SyncStatus::ServerUnreachable)
+        );
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(NEW),
+                &NewestState::Version(NEW),
+                None
+            ),
+            Some(SyncStatus::UpToDate)
+        );
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(OLD),
+                &NewestState::Version(NEW),
+                None
+            ),
+            Some(SyncStatus::UpdateAvailable(NEW))
+        );
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(NEW),
+                &NewestState::Version(OLD),
+                None
+            ),
+            Some(SyncStatus::NewerThanServer)
+        );
+    }
+
+    #[test]
+    fn pinning_only_hides_updates() {
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(OLD),
+                &NewestState::Version(NEW),
+                Some(OLD)
+            ),
+            Some(SyncStatus::Pinned(OLD))
+        );
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(NEW),
+                &NewestState::Version(OLD),
+                Some(NEW)
+            ),
+            Some(SyncStatus::NewerThanServer)
+        );
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(NEW),
+                &NewestState::Version(NEW),
+                Some(NEW)
+            ),
+            Some(SyncStatus::UpToDate)
+        );
+        // A pin of another version doesn't apply anymore
+        assert_eq!(
+            sync_status(
+                &InstalledState::Installed(OLD),
+                &NewestState::Version(NEW),
+                Some(NEW)
+            ),
+            Some(SyncStatus::UpdateAvailable(NEW))
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use crate::gui::Inner;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
+use std::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub(super) type ManagedWatcher = Debouncer<RecommendedWatcher>;
+
+impl Inner {
+    /// Watches the Managed dir, to notice dlls being swapped outside the app, e.g. in the file explorer.
+    /// Replaces the previous watcher, so this has to be called again whenever the game dir changes.
+    #[instrument(skip(self))]
+    pub(super) fn watch_managed_dir(&mut self) {
+        self.managed_watcher = None;
+
+        if !self.settings.game_dir.is_set() {
+            return;
+        }
+
+        let managed = self.settings.game_dir.managed.clone();
+        let weak_self = self.weak_self.clone();
+
+        let watcher = new_debouncer(WATCH_DEBOUNCE, move |result: DebounceEventResult| {
+            if let Some(app) = weak_self.upgrade() {
+                app.lock().unwrap().handle_managed_dir_change(result);
+            }
+        })
+        .and_then(|mut debouncer| {
+            debouncer
+                .watcher()
+                .watch(&managed, RecursiveMode::NonRecursive)?;
+            Ok(debouncer)
+        });
+
+        match watcher {
+            Ok(watcher) => self.managed_watcher = Some(watcher),
+            Err(err) => error!(?err, ?managed, "Couldn't watch Managed dir"),
+        }
+    }
+
+    #[instrument(skip_all)]
+    fn handle_managed_dir_change(&mut self, result: DebounceEventResult) {
+        let events = match result {
+            Ok(events) => events,
+            Err(err) => {
+                warn!(?err, "Error watching Managed dir");
+                return;
+            }
+        };
+
+        let dll_changed = events.iter().any(|event| {
+            event
+                .path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("dll"))
+        });
+        if !dll_changed {
+            return;
+        }
+
+        // Changes made by the app itself are reported after the debounce time,
+        // and the dlls get updated anyway after each of those operations
+        let recently_busy = self
+            .last_busy
+            .is_some_and(|last_busy| last_busy.elapsed() < WATCH_DEBOUNCE * 2);
+        if self.is_busy() || recently_busy {
+            debug!("Ignoring changes made by the app itself");
+            return;
+        }
+
+        info!("Dlls changed outside the app, updating");
+        self.update_dlls();
+        self.egui_ctx.request_repaint();
+    }
+}
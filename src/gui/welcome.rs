@@ -0,0 +1,25 @@
+use crate::gui::{AppModal, Inner};
+use crate::lang::tr;
+use eframe::egui::{Align, Layout};
+use tracing::{info, instrument};
+
+impl Inner {
+    /// Explains the installer on first launch, until the user chooses not to see it again.
+    #[instrument(skip(self))]
+    pub(super) fn show_welcome(&mut self) {
+        info!("Showing welcome");
+        self.show_modal_ui(AppModal::new().dismissable(true), |app, ui, modal| {
+            ui.heading(tr("welcome_title"));
+            ui.label(tr("welcome_explanation"));
+            ui.label(tr("welcome_definitive_edition"));
+            ui.label(tr("welcome_auto_update"));
+
+            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                if ui.button(tr("dont_show_again")).clicked() {
+                    app.settings.seen_welcome = true;
+                    modal.close();
+                }
+            });
+        });
+    }
+}
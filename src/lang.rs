@@ -0,0 +1,668 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    fn table(self) -> fn(&str) -> Option<&'static str> {
+        match self {
+            Language::English => english,
+            Language::German => german,
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Language::English => f.write_str("English"),
+            Language::German => f.write_str("Deutsch"),
+        }
+    }
+}
+
+static CURRENT_LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+pub fn set_language(language: Language) {
+    CURRENT_LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn current_language() -> Language {
+    let current = CURRENT_LANGUAGE.load(Ordering::Relaxed);
+    Language::ALL
+        .into_iter()
+        .find(|&language| language as u8 == current)
+        .unwrap_or_default()
+}
+
+/// Translates `key` into the current language.
+/// Falls back to English for missing translations, and to the key itself for unknown keys.
+pub fn tr(key: &'static str) -> &'static str {
+    current_language().table()(key)
+        .or_else(|| english(key))
+        .unwrap_or(key)
+}
+
+/// Translates a sentence containing a number, `{count}` in the translation is replaced by `count`.
+/// Each language has a sentence for one, with the key suffixed by `_one`, and for any other count, suffixed by `_other`.
+pub fn tr_count(key: &str, count: u64) -> String {
+    tr_count_in(current_language(), key, count)
+}
+
+fn tr_count_in(language: Language, key: &str, count: u64) -> String {
+    // English and German both only distinguish singular and plural
+    let suffix = if count == 1 { "one" } else { "other" };
+    let key = format!("{key}_{suffix}");
+    let text = language.table()(&key)
+        .or_else(|| english(&key))
+        .unwrap_or(&key);
+    text.replace("{count}", &count.to_string())
+}
+
+fn english(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "settings" => "Settings",
+        "game_dir_missing" => {
+            "The game folder can't be found anymore. Was the game moved or uninstalled?"
+        }
+        "game_not_found" => {
+            "Installation of Ori and the Blind Forest: Definitive Edition not found."
+        }
+        "de_only_note" => {
+            "Note: The randomizer is only compatible with the Definitive Edition, not the original."
+        }
+        "select_install_dir" => "Please select the installation directory:",
+        "error" => "Error",
+        "ok" => "Ok",
+        "okay" => "Okay",
+        "welcome_title" => "Welcome to the Ori DE Randomizer installer",
+        "welcome_explanation" => {
+            "This tool installs the randomizer into your game, keeps a backup of the vanilla game \
+            and lets you switch between randomizer versions."
+        }
+        "welcome_definitive_edition" => {
+            "The randomizer requires Ori and the Blind Forest: Definitive Edition, \
+            the original release isn't supported."
+        }
+        "welcome_auto_update" => {
+            "The installer updates itself when it starts and lets you know about new randomizer versions, \
+            updating the installer can be turned off in the settings."
+        }
+        "dont_show_again" => "Don't show again",
+        "tab_game_settings" => "Game Settings",
+        "show_logs" => "Show logs",
+        "log_level" => "Log level",
+        "report_problem" => "Report a problem",
+        "switch_to_light" => "Switch to light mode",
+        "switch_to_dark" => "Switch to dark mode",
+        "launch_game" => "Launch game",
+        "launch_disabled_busy" => "Please wait until the current operation has finished",
+        "close" => "Close",
+        "failed_load_versions" => "Failed to load installed versions",
+        "compare_active" => "Compared to the active version:",
+        "compare_latest" => "Compared to the latest version:",
+        "compare_newer" => "newer",
+        "compare_older" => "older",
+        "compare_same" => "the same",
+        "compare_unavailable" => "can't be compared",
+        "switch_version" => "Switch version",
+        "no_versions_yet" => "Installed versions will appear here after installing the randomizer",
+        "rando_only" => "Only show rando versions",
+        "restore_vanilla" => "Restore Vanilla",
+        "export_dll" => "Export DLL...",
+        "download_only" => "Download only...",
+        "download_only_hover" => "Save the latest randomizer dll somewhere without installing it",
+        "downloading_dll" => "Downloading DLL...",
+        "failed_download_only" => "Failed to download the DLL",
+        "copy_hash" => "Copy full SHA-256 hash",
+        "failed_export_dll" => "Failed to export the DLL",
+        "reinstall" => "Reinstall",
+        "version_history" => "Installed versions",
+        "reinstall_hover" => {
+            "Installs the current version again, e.g. if the game files got corrupted"
+        }
+        "stray_dlls" => {
+            "⚠ These extra copies of the game assembly may confuse the game, consider removing them:"
+        }
+        "open_seed_folder" => "Open seed folder",
+        "switching_version" => "Switching version...",
+        "failed_switch_version" => "Failed to switch version",
+        "failed_download_update" => {
+            "Failed to download the randomizer, check your internet connection"
+        }
+        "failed_install_update" => "Failed to install the randomizer into the game directory",
+        "see_log" => "See the log file for details.",
+        "missing_active_dll" => {
+            "No randomizer or vanilla dll is active, the game won't start. \
+                This can happen if a previous installation was interrupted. \
+                Do you want to restore a backup?"
+        }
+        "restore" => "Restore",
+        "vanilla_backup_created" => {
+            "A backup of the vanilla game was created. \
+                You can always switch back to it in the version selector."
+        }
+        "not_now" => "Not now",
+        "vanilla" => "Vanilla",
+        "rando" => "Rando",
+        "none" => "<None>",
+        "loading_versions" => "Loading installed versions...",
+        "install_randomizer" => "Install Randomizer",
+        "missing_game_dll" => {
+            "The game files seem incomplete. \
+                Please launch the game once or verify its files in Steam before installing."
+        }
+        "install_anyway" => "Install anyway",
+        "not_an_ori_install" => {
+            "The selected game folder doesn't look like an Ori install, please select the game's folder."
+        }
+        "network_location_unavailable" => {
+            "The game folder is on a network location that's currently unavailable."
+        }
+        "dll_quarantined" => {
+            "The randomizer was removed right after installing. \
+                Most likely your antivirus quarantined it, because it's an unsigned modded file. \
+                Please add an exclusion for the game folder and install again."
+        }
+        "add_antivirus_exclusion" => "How to add an exclusion to Windows Security",
+        "pin_version" => "Pin this version, so updates aren't offered",
+        "pinned_to" => "Pinned to",
+        "unpin" => "Unpin",
+        "install_without_vanilla_warning" => {
+            "No vanilla game file was found, so no backup can be made. \
+                To uninstall the randomizer later, you'll have to verify the game files in Steam."
+        }
+        "rando_installed" => "✔ Rando installed",
+        "checking_updates" => "Checking for updates...",
+        "error_checking_updates" => "✖ Error checking for updates",
+        "site_maintenance" => "✖ The randomizer site appears to be under maintenance",
+        "already_newest" => "✔ Already on newest version",
+        "sync_checking" => "Checking...",
+        "sync_not_installed" => "No randomizer installed",
+        "sync_up_to_date" => "You're up to date",
+        "sync_update_available" => "Update available:",
+        "sync_newer_than_server" => "Newer than the released version",
+        "sync_custom_build" => "Custom build installed",
+        "custom_build" => "custom build",
+        "sync_server_unreachable" => "Couldn't check for updates",
+        "update_to" => "Update to",
+        "replace_custom_build" => "Replace custom build with",
+        "replace_custom_build_warning" => {
+            "The installed randomizer is not a recognized release, e.g. a custom or development build. \
+                It will be backed up before it is replaced, so you can switch back to it later."
+        }
+        "checked_just_now" => "checked just now",
+        "checked_minutes_ago_one" => "checked {count} minute ago",
+        "checked_minutes_ago_other" => "checked {count} minutes ago",
+        "checked_hours_ago_one" => "checked {count} hour ago",
+        "checked_hours_ago_other" => "checked {count} hours ago",
+        "checked_days_ago_one" => "checked {count} day ago",
+        "checked_days_ago_other" => "checked {count} days ago",
+        "released" => "Released",
+        "installing_randomizer" => "Installing Randomizer...",
+        "step_download" => "Download the randomizer",
+        "step_install" => "Install the randomizer",
+        "step_prune_backups" => "Clean up old backups",
+        "step_find_vanilla" => "Find the vanilla backup",
+        "step_restore_vanilla" => "Restore vanilla",
+        "version" => "version",
+        "theme" => "Theme",
+        "theme_system" => "System",
+        "theme_dark" => "Dark",
+        "theme_light" => "Light",
+        "accent_color" => "Accent color",
+        "custom" => "Custom",
+        "language" => "Language",
+        "game_install_dir" => "Game installation directory",
+        "auto_detect" => "Auto-Detect",
+        "detect_via" => "Detect via...",
+        "reveal" => "Reveal",
+        "choose" => "Choose...",
+        "launch_type" => "Game launch type",
+        "steam_not_found" => "⚠ Steam not found",
+        "exe_not_found" => "⚠ oriDE.exe not found",
+        "auto_update" => "Auto-Update",
+        "launch_after" => "Launch game after",
+        "launch_after_install" => "Install/Update",
+        "launch_after_switch" => "Switching version",
+        "minimize_to_tray" => "Minimize to tray",
+        "always_on_top" => "Keep window on top",
+        "compact_mode" => "Compact mode",
+        "compact_mode_hover" => "Only show installing and updating the randomizer",
+        "developer_mode" => "Developer mode",
+        "developer_mode_hover" => "Show paths, hashes and classifications for debugging",
+        "developer_info" => "Developer info",
+        "managed_dir" => "Managed directory:",
+        "active_dll_hash" => "Active dll SHA-256:",
+        "hash_all_dlls" => "Hash all dlls",
+        "server_urls" => "Server URLs:",
+        "restore_vanilla_on_exit" => "Restore vanilla on close",
+        "restore_vanilla_on_exit_hover" => {
+            "When closing the app, the vanilla game is restored if a backup of it exists"
+        }
+        "color_blind_palette" => "Color-blind friendly colors",
+        "color_blind_palette_hover" => {
+            "Statuses use colors that don't rely on telling red and green apart"
+        }
+        "max_backups" => "Rando backups to keep",
+        "backup_location" => "Store backups",
+        "update_check_interval" => "Check for updates every",
+        "update_check_interval_hover" => "0 only checks when the app starts",
+        "backup_location_managed" => "In the game directory",
+        "backup_location_app_data" => "In the app data",
+        "tray_open" => "Open",
+        "tray_quit" => "Quit",
+        "game_dir_found_below" => {
+            "The selected directory isn't the game itself, but the game was found in this folder inside it:"
+        }
+        "use_this_folder" => "Use this folder",
+        "invalid_game_dir" => {
+            "The selected directory does not appear to be a valid installation of \
+                Ori and the Blind Forest: Definitive Edition. \
+                Please select another directory."
+        }
+        "uninstall" => "Uninstall randomizer",
+        "preparing_uninstall" => "Preparing uninstall...",
+        "failed_prepare_uninstall" => "Failed to prepare uninstall",
+        "uninstall_restores_vanilla" => "The vanilla game will be restored.",
+        "uninstall_no_backups" => "There are no randomizer backups to delete.",
+        "uninstall_deleted_files" => "Optionally, these randomizer files will be deleted:",
+        "uninstall_keep_backups" => "Uninstall",
+        "uninstall_delete_backups" => "Uninstall and delete",
+        "uninstalling" => "Uninstalling randomizer...",
+        "steam_verify" => "Verify game files",
+        "steam_verify_hover" => {
+            "Restore vanilla, verify the game files in Steam and reinstall the randomizer"
+        }
+        "steam_verify_explanation" => {
+            "Verifying the game files in Steam replaces the randomizer with the vanilla game. \
+                To verify cleanly, vanilla is restored first. The randomizer can be reinstalled afterwards."
+        }
+        "steam_verify_restore_vanilla" => "Restore vanilla",
+        "restoring_vanilla" => "Restoring vanilla...",
+        "failed_restore_vanilla" => "Failed to restore vanilla",
+        "steam_verify_now" => {
+            "Vanilla was restored. Now verify the game files in Steam: \
+                right click the game in your library, Properties, Installed Files, Verify integrity of game files. \
+                Once it's done, reinstall the randomizer."
+        }
+        "steam_verify_open" => "Open in Steam",
+        "steam_verify_done" => "Reinstall randomizer",
+        "steam_verify_keep_vanilla" => "Keep vanilla",
+        "failed_open_steam_verification" => "Failed to open the verification in Steam",
+        "create_full_backup" => "Create full backup...",
+        "create_full_backup_hover" => "Saves the whole Managed directory as a zip file",
+        "creating_full_backup" => "Creating full backup...",
+        "failed_full_backup" => "Failed to create the full backup",
+        "failed_uninstall" => "Failed to uninstall randomizer",
+        "cancel" => "Cancel",
+        "dismiss" => "Dismiss",
+        "close_while_busy" => {
+            "An operation is still in progress. \
+                Quitting now could leave the game files broken."
+        }
+        "quit_anyway" => "Quit anyway",
+        "keep_waiting" => "Keep waiting",
+        "self_update_not_writable" => {
+            "The installer couldn't update itself, because its folder isn't writable. \
+                Please move the installer to a folder you can write to, e.g. your Downloads folder:"
+        }
+        "original_game" => {
+            "This looks like the original Ori and the Blind Forest, not the Definitive Edition. \
+                The randomizer only supports the Definitive Edition."
+        }
+        "cloud_sync_warning" => {
+            "⚠ The game is in a synced folder. \
+                Sync clients can interfere with installing the randomizer"
+        }
+        "settings_not_saved" => "⚠ Settings couldn't be saved, changes won't persist",
+        "open_settings" => "Open settings:",
+        "file_not_created_yet" => {
+            "This file doesn't exist yet. \
+                The game creates it once you've run it with the randomizer."
+        }
+        "create_empty_file" => "Create empty file",
+        "failed_create_file" => "Failed to create the file",
+        "randomizer" => "Randomizer",
+        "open_controls" => "Open Controls:",
+        "vanilla_kbm" => "Vanilla (KBM)",
+        "vanilla_controller" => "Vanilla (Controller)",
+        "controller_remaps" => "Controller Remaps",
+        _ => return None,
+    })
+}
+
+fn german(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "settings" => "Einstellungen",
+        "game_dir_missing" => {
+            "Der Spielordner wurde nicht mehr gefunden. Wurde das Spiel verschoben oder deinstalliert?"
+        }
+        "game_not_found" => {
+            "Keine Installation von Ori and the Blind Forest: Definitive Edition gefunden."
+        }
+        "de_only_note" => {
+            "Hinweis: Der Randomizer ist nur mit der Definitive Edition kompatibel, nicht mit dem Original."
+        }
+        "select_install_dir" => "Bitte wähle das Installationsverzeichnis:",
+        "error" => "Fehler",
+        "ok" => "Ok",
+        "okay" => "Okay",
+        "welcome_title" => "Willkommen beim Installer des Ori DE Randomizers",
+        "welcome_explanation" => {
+            "Dieses Tool installiert den Randomizer in dein Spiel, behält ein Backup des Vanilla-Spiels \
+            und lässt dich zwischen Randomizer-Versionen wechseln."
+        }
+        "welcome_definitive_edition" => {
+            "Der Randomizer benötigt Ori and the Blind Forest: Definitive Edition, \
+            die ursprüngliche Version wird nicht unterstützt."
+        }
+        "welcome_auto_update" => {
+            "Der Installer aktualisiert sich beim Start selbst und weist auf neue Randomizer-Versionen hin, \
+            das Aktualisieren des Installers lässt sich in den Einstellungen abschalten."
+        }
+        "dont_show_again" => "Nicht mehr anzeigen",
+        "tab_game_settings" => "Spieleinstellungen",
+        "show_logs" => "Logs anzeigen",
+        "log_level" => "Log-Level",
+        "report_problem" => "Problem melden",
+        "switch_to_light" => "Zum hellen Modus wechseln",
+        "switch_to_dark" => "Zum dunklen Modus wechseln",
+        "launch_game" => "Spiel starten",
+        "launch_disabled_busy" => "Bitte warte, bis der aktuelle Vorgang abgeschlossen ist",
+        "close" => "Schließen",
+        "failed_load_versions" => "Installierte Versionen konnten nicht geladen werden",
+        "compare_active" => "Im Vergleich zur aktiven Version:",
+        "compare_latest" => "Im Vergleich zur neuesten Version:",
+        "compare_newer" => "neuer",
+        "compare_older" => "älter",
+        "compare_same" => "gleich",
+        "compare_unavailable" => "nicht vergleichbar",
+        "switch_version" => "Version wechseln",
+        "no_versions_yet" => {
+            "Installierte Versionen erscheinen hier, sobald der Randomizer installiert ist"
+        }
+        "rando_only" => "Nur Rando-Versionen anzeigen",
+        "restore_vanilla" => "Vanilla wiederherstellen",
+        "export_dll" => "DLL exportieren...",
+        "download_only" => "Nur herunterladen...",
+        "download_only_hover" => "Die neueste Randomizer-DLL speichern, ohne sie zu installieren",
+        "downloading_dll" => "DLL wird heruntergeladen...",
+        "failed_download_only" => "DLL konnte nicht heruntergeladen werden",
+        "copy_hash" => "Vollständigen SHA-256-Hash kopieren",
+        "failed_export_dll" => "DLL konnte nicht exportiert werden",
+        "reinstall" => "Neu installieren",
+        "version_history" => "Installierte Versionen",
+        "reinstall_hover" => {
+            "Installiert die aktuelle Version erneut, z.B. wenn die Spieldateien beschädigt sind"
+        }
+        "stray_dlls" => {
+            "⚠ Diese zusätzlichen Kopien der Spiel-Assembly können das Spiel stören, entferne sie am besten:"
+        }
+        "open_seed_folder" => "Seed-Ordner öffnen",
+        "switching_version" => "Version wird gewechselt...",
+        "failed_switch_version" => "Version konnte nicht gewechselt werden",
+        "failed_download_update" => {
+            "Randomizer konnte nicht heruntergeladen werden, bitte Internetverbindung prüfen"
+        }
+        "failed_install_update" => "Randomizer konnte nicht im Spielordner installiert werden",
+        "see_log" => "Details stehen in der Logdatei.",
+        "missing_active_dll" => {
+            "Es ist weder eine Randomizer- noch eine Vanilla-DLL aktiv, das Spiel startet nicht. \
+                Das kann passieren, wenn eine vorherige Installation unterbrochen wurde. \
+                Möchtest du ein Backup wiederherstellen?"
+        }
+        "restore" => "Wiederherstellen",
+        "vanilla_backup_created" => {
+            "Ein Backup des Vanilla-Spiels wurde erstellt. \
+                Du kannst jederzeit in der Versionsauswahl dorthin zurückwechseln."
+        }
+        "not_now" => "Nicht jetzt",
+        "none" => "<Keine>",
+        "loading_versions" => "Installierte Versionen werden geladen...",
+        "install_randomizer" => "Randomizer installieren",
+        "missing_game_dll" => {
+            "Die Spieldateien scheinen unvollständig zu sein. \
+                Bitte starte das Spiel einmal oder überprüfe seine Dateien in Steam vor der Installation."
+        }
+        "install_anyway" => "Trotzdem installieren",
+        "not_an_ori_install" => {
+            "Der ausgewählte Spielordner sieht nicht nach einer Ori-Installation aus, bitte wähle den Ordner des Spiels."
+        }
+        "network_location_unavailable" => {
+            "Der Spielordner liegt an einem Netzwerkort, der gerade nicht erreichbar ist."
+        }
+        "dll_quarantined" => {
+            "Der Randomizer wurde direkt nach der Installation entfernt. \
+                Wahrscheinlich hat dein Virenschutz ihn in Quarantäne verschoben, weil er eine unsignierte modifizierte Datei ist. \
+                Bitte füge eine Ausnahme für den Spielordner hinzu und installiere erneut."
+        }
+        "add_antivirus_exclusion" => "Ausnahme zu Windows-Sicherheit hinzufügen",
+        "pin_version" => "Diese Version festhalten, damit keine Updates angeboten werden",
+        "pinned_to" => "Festgehalten auf",
+        "unpin" => "Lösen",
+        "install_without_vanilla_warning" => {
+            "Es wurde keine Vanilla-Spieldatei gefunden, daher kann kein Backup erstellt werden. \
+                Um den Randomizer später zu deinstallieren, musst du die Spieldateien in Steam überprüfen."
+        }
+        "rando_installed" => "✔ Rando installiert",
+        "checking_updates" => "Suche nach Updates...",
+        "error_checking_updates" => "✖ Fehler bei der Suche nach Updates",
+        "site_maintenance" => "✖ Die Randomizer-Seite wird anscheinend gerade gewartet",
+        "already_newest" => "✔ Neueste Version bereits installiert",
+        "sync_checking" => "Wird geprüft...",
+        "sync_not_installed" => "Kein Randomizer installiert",
+        "sync_up_to_date" => "Du bist auf dem neuesten Stand",
+        "sync_update_available" => "Update verfügbar:",
+        "sync_newer_than_server" => "Neuer als die veröffentlichte Version",
+        "sync_custom_build" => "Eigener Build installiert",
+        "custom_build" => "eigener Build",
+        "sync_server_unreachable" => "Suche nach Updates nicht möglich",
+        "update_to" => "Aktualisieren auf",
+        "replace_custom_build" => "Eigenen Build ersetzen durch",
+        "replace_custom_build_warning" => {
+            "Der installierte Randomizer ist keine bekannte Version, z.B. ein eigener oder Entwicklungs-Build. \
+                Er wird vor dem Ersetzen gesichert, du kannst also später zu ihm zurückwechseln."
+        }
+        "checked_just_now" => "gerade eben geprüft",
+        "checked_minutes_ago_one" => "vor {count} Minute geprüft",
+        "checked_minutes_ago_other" => "vor {count} Minuten geprüft",
+        "checked_hours_ago_one" => "vor {count} Stunde geprüft",
+        "checked_hours_ago_other" => "vor {count} Stunden geprüft",
+        "checked_days_ago_one" => "vor {count} Tag geprüft",
+        "checked_days_ago_other" => "vor {count} Tagen geprüft",
+        "released" => "Veröffentlicht am",
+        "installing_randomizer" => "Randomizer wird installiert...",
+        "step_download" => "Randomizer herunterladen",
+        "step_install" => "Randomizer installieren",
+        "step_prune_backups" => "Alte Backups aufräumen",
+        "step_find_vanilla" => "Vanilla-Backup suchen",
+        "step_restore_vanilla" => "Vanilla wiederherstellen",
+        "theme" => "Design",
+        "theme_system" => "System",
+        "theme_dark" => "Dunkel",
+        "theme_light" => "Hell",
+        "accent_color" => "Akzentfarbe",
+        "custom" => "Eigene",
+        "language" => "Sprache",
+        "game_install_dir" => "Installationsverzeichnis des Spiels",
+        "auto_detect" => "Automatisch erkennen",
+        "detect_via" => "Erkennen über...",
+        "reveal" => "Anzeigen",
+        "choose" => "Auswählen...",
+        "launch_type" => "Startart des Spiels",
+        "steam_not_found" => "⚠ Steam nicht gefunden",
+        "exe_not_found" => "⚠ oriDE.exe nicht gefunden",
+        "auto_update" => "Automatisch aktualisieren",
+        "launch_after" => "Spiel starten nach",
+        "launch_after_install" => "Installation/Update",
+        "launch_after_switch" => "Versionswechsel",
+        "minimize_to_tray" => "In den Infobereich minimieren",
+        "always_on_top" => "Fenster im Vordergrund halten",
+        "compact_mode" => "Kompakter Modus",
+        "compact_mode_hover" => "Nur Installation und Aktualisierung des Randomizers anzeigen",
+        "developer_mode" => "Entwicklermodus",
+        "developer_mode_hover" => "Pfade, Hashes und Klassifizierungen zur Fehlersuche anzeigen",
+        "developer_info" => "Entwickler-Infos",
+        "managed_dir" => "Managed-Ordner:",
+        "active_dll_hash" => "SHA-256 der aktiven DLL:",
+        "hash_all_dlls" => "Alle DLLs hashen",
+        "server_urls" => "Server-URLs:",
+        "restore_vanilla_on_exit" => "Beim Schließen Vanilla wiederherstellen",
+        "restore_vanilla_on_exit_hover" => {
+            "Beim Schließen der App wird das Vanilla-Spiel wiederhergestellt, falls ein Backup davon existiert"
+        }
+        "color_blind_palette" => "Farbenblind-freundliche Farben",
+        "color_blind_palette_hover" => {
+            "Status-Anzeigen nutzen Farben, die nicht auf Rot und Grün angewiesen sind"
+        }
+        "max_backups" => "Anzahl behaltener Rando-Backups",
+        "backup_location" => "Backups speichern",
+        "update_check_interval" => "Nach Updates suchen alle",
+        "update_check_interval_hover" => "Bei 0 wird nur beim Start der App gesucht",
+        "backup_location_managed" => "Im Spielordner",
+        "backup_location_app_data" => "In den App-Daten",
+        "tray_open" => "Öffnen",
+        "tray_quit" => "Beenden",
+        "game_dir_found_below" => {
+            "Das ausgewählte Verzeichnis ist nicht das Spiel selbst, aber das Spiel wurde in diesem Ordner darin gefunden:"
+        }
+        "use_this_folder" => "Diesen Ordner verwenden",
+        "invalid_game_dir" => {
+            "Das ausgewählte Verzeichnis scheint keine gültige Installation von \
+                Ori and the Blind Forest: Definitive Edition zu sein. \
+                Bitte wähle ein anderes Verzeichnis."
+        }
+        "uninstall" => "Randomizer deinstallieren",
+        "preparing_uninstall" => "Deinstallation wird vorbereitet...",
+        "failed_prepare_uninstall" => "Deinstallation konnte nicht vorbereitet werden",
+        "uninstall_restores_vanilla" => "Das Vanilla-Spiel wird wiederhergestellt.",
+        "uninstall_no_backups" => "Es gibt keine Randomizer-Backups zum Löschen.",
+        "uninstall_deleted_files" => "Optional werden diese Randomizer-Dateien gelöscht:",
+        "uninstall_keep_backups" => "Deinstallieren",
+        "uninstall_delete_backups" => "Deinstallieren und löschen",
+        "uninstalling" => "Randomizer wird deinstalliert...",
+        "steam_verify" => "Spieldateien überprüfen",
+        "steam_verify_hover" => {
+            "Vanilla wiederherstellen, die Spieldateien in Steam überprüfen und den Randomizer neu installieren"
+        }
+        "steam_verify_explanation" => {
+            "Die Überprüfung der Spieldateien in Steam ersetzt den Randomizer durch das Vanilla-Spiel. \
+                Damit die Überprüfung sauber durchläuft, wird zuerst Vanilla wiederhergestellt. \
+                Danach kann der Randomizer neu installiert werden."
+        }
+        "steam_verify_restore_vanilla" => "Vanilla wiederherstellen",
+        "restoring_vanilla" => "Vanilla wird wiederhergestellt...",
+        "failed_restore_vanilla" => "Vanilla konnte nicht wiederhergestellt werden",
+        "steam_verify_now" => {
+            "Vanilla wurde wiederhergestellt. Überprüfe jetzt die Spieldateien in Steam: \
+                Rechtsklick auf das Spiel in deiner Bibliothek, Eigenschaften, Installierte Dateien, \
+                Spieldateien auf Fehler überprüfen. Installiere danach den Randomizer neu."
+        }
+        "steam_verify_open" => "In Steam öffnen",
+        "steam_verify_done" => "Randomizer neu installieren",
+        "steam_verify_keep_vanilla" => "Vanilla behalten",
+        "failed_open_steam_verification" => "Die Überprüfung konnte nicht in Steam geöffnet werden",
+        "create_full_backup" => "Vollständiges Backup erstellen...",
+        "create_full_backup_hover" => "Speichert den gesamten Managed-Ordner als Zip-Datei",
+        "creating_full_backup" => "Vollständiges Backup wird erstellt...",
+        "failed_full_backup" => "Vollständiges Backup konnte nicht erstellt werden",
+        "failed_uninstall" => "Randomizer konnte nicht deinstalliert werden",
+        "cancel" => "Abbrechen",
+        "dismiss" => "Ausblenden",
+        "close_while_busy" => {
+            "Ein Vorgang läuft noch. \
+                Wenn du jetzt beendest, könnten die Spieldateien beschädigt werden."
+        }
+        "quit_anyway" => "Trotzdem beenden",
+        "keep_waiting" => "Weiter warten",
+        "self_update_not_writable" => {
+            "Der Installer konnte sich nicht aktualisieren, weil sein Ordner nicht beschreibbar ist. \
+                Bitte verschiebe den Installer in einen beschreibbaren Ordner, z.B. deinen Downloads-Ordner:"
+        }
+        "original_game" => {
+            "Das sieht nach dem ursprünglichen Ori and the Blind Forest aus, nicht nach der Definitive Edition. \
+                Der Randomizer unterstützt nur die Definitive Edition."
+        }
+        "cloud_sync_warning" => {
+            "⚠ Das Spiel liegt in einem synchronisierten Ordner. \
+                Sync-Programme können die Installation des Randomizers stören"
+        }
+        "settings_not_saved" => {
+            "⚠ Einstellungen konnten nicht gespeichert werden, Änderungen bleiben nicht erhalten"
+        }
+        "open_settings" => "Einstellungen öffnen:",
+        "file_not_created_yet" => {
+            "Diese Datei existiert noch nicht. \
+                Das Spiel erstellt sie, sobald du es mit dem Randomizer gestartet hast."
+        }
+        "create_empty_file" => "Leere Datei erstellen",
+        "failed_create_file" => "Die Datei konnte nicht erstellt werden",
+        "open_controls" => "Steuerung öffnen:",
+        "vanilla_kbm" => "Vanilla (Tastatur & Maus)",
+        "controller_remaps" => "Controller-Belegung",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_singular_or_plural_sentence() {
+        assert_eq!(
+            tr_count_in(Language::English, "checked_days_ago", 1),
+            "checked 1 day ago"
+        );
+        assert_eq!(
+            tr_count_in(Language::English, "checked_days_ago", 3),
+            "checked 3 days ago"
+        );
+        assert_eq!(
+            tr_count_in(Language::English, "checked_minutes_ago", 0),
+            "checked 0 minutes ago"
+        );
+        assert_eq!(
+            tr_count_in(Language::German, "checked_hours_ago", 1),
+            "vor 1 Stunde geprüft"
+        );
+        assert_eq!(
+            tr_count_in(Language::German, "checked_days_ago", 21),
+            "vor 21 Tagen geprüft"
+        );
+    }
+
+    #[test]
+    fn every_count_sentence_is_translated() {
+        for key in [
+            "checked_minutes_ago",
+            "checked_hours_ago",
+            "checked_days_ago",
+        ] {
+            for suffix in ["one", "other"] {
+                let key = format!("{key}_{suffix}");
+                for language in Language::ALL {
+                    let text = language.table()(&key);
+                    assert!(
+                        text.is_some_and(|text| text.contains("{count}")),
+                        "{language} {key}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_count_key_falls_back_to_key() {
+        assert_eq!(tr_count_in(Language::German, "unknown", 2), "unknown_other");
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::env::temp_dir;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing::{error, info};
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Info,
+    #[default]
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    fn directive(self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Off => f.write_str("Off"),
+            LogLevel::Error => f.write_str("Error"),
+            LogLevel::Info => f.write_str("Info"),
+            LogLevel::Debug => f.write_str("Debug"),
+            LogLevel::Trace => f.write_str("Trace"),
+        }
+    }
+}
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+static FILTER_HANDLE: OnceLock<FilterHandle> = OnceLock::new();
+
+/// Makes the filter of [`set_log_level`] reloadable, has to be the first layer of the subscriber.
+pub fn reloadable_filter(filter: EnvFilter) -> reload::Layer<EnvFilter, Registry> {
+    let (layer, handle) = reload::Layer::new(filter);
+    _ = FILTER_HANDLE.set(handle);
+    layer
+}
+
+/// Reconfigures the log filter at runtime.
+/// [`LogLevel::Off`] also deletes the log file, it is created again once something is logged.
+pub fn set_log_level(level: LogLevel) {
+    let Some(handle) = FILTER_HANDLE.get() else {
+        return;
+    };
+
+    if level != LogLevel::Off {
+        FILE_LOGGING_ENABLED.store(true, Ordering::Relaxed);
+    }
+
+    if let Err(err) = handle.reload(EnvFilter::new(level.directive())) {
+        error!(?err, "Couldn't change log level");
+        return;
+    }
+    info!(?level, "Changed log level");
+
+    if level == LogLevel::Off {
+        FILE_LOGGING_ENABLED.store(false, Ordering::Relaxed);
+        let mut file = LOG_FILE.lock().unwrap();
+        *file = None;
+        if let Err(err) = std::fs::remove_file(log_file_path()) {
+            if err.kind() != io::ErrorKind::NotFound {
+                eprintln!("Can't delete log file: {err:?}");
+            }
+        }
+    }
+}
+
+fn log_file_path() -> PathBuf {
+    temp_dir().join("ori-de-randomizer.log")
+}
+
+/// The log file, if anything has been logged to it.
+pub fn existing_log_file() -> Option<PathBuf> {
+    let path = log_file_path();
+    path.is_file().then_some(path)
+}
+
+static FILE_LOGGING_ENABLED: AtomicBool = AtomicBool::new(true);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Writes to the log file, which only gets created once something is logged.
+pub struct LogFileWriter;
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !FILE_LOGGING_ENABLED.load(Ordering::Relaxed) {
+            return Ok(buf.len());
+        }
+
+        let mut file = LOG_FILE.lock().unwrap();
+        if file.is_none() {
+            *file = Some(File::create(log_file_path())?);
+        }
+
+        match file.as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,22 +1,28 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 #![warn(clippy::pedantic)]
 
-use crate::game::{search_for_game_dir, verify_game_dir};
+use crate::cli_output::CliOutput;
+use crate::dll_classifier::{DllClassification, RandoVersion, classify_bytes};
+use crate::dll_management::{
+    InstallPlan, OriDllKind, install_dll, plan_install, prune_backups, search_game_dir,
+};
+use crate::game::{GameDir, search_for_game_dir, verify_game_dir};
 use crate::gui::run_gui;
-use crate::self_update::self_update;
+use crate::logging::{LogFileWriter, reloadable_filter, set_log_level};
+use crate::self_update::{ExeDirNotWritable, self_update};
 use crate::settings::Settings;
+use crate::single_instance::{acquire_instance, focus_existing_window};
 use color_eyre::Result;
-use color_eyre::eyre::bail;
+use color_eyre::eyre::{OptionExt, bail, eyre};
 use std::any::Any;
 use std::default::Default;
-use std::env::temp_dir;
-use std::fs::File;
+use std::ffi::OsString;
 use std::os::windows::ffi::OsStrExt;
 use std::path::PathBuf;
 use std::ptr::copy_nonoverlapping;
-use std::sync::OnceLock;
+use std::time::Duration;
 use std::{io, ptr};
-use tracing::{debug, error, info, info_span, instrument};
+use tracing::{error, info, info_span, instrument, warn};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -25,46 +31,116 @@ use windows_sys::Win32::Foundation::{BOOL, POINT, WPARAM};
 use windows_sys::Win32::System::Memory::{GetProcessHeap, HEAP_ZERO_MEMORY, HeapAlloc};
 use windows_sys::Win32::UI::WindowsAndMessaging::{FindWindowA, PostMessageA, WM_DROPFILES};
 
+mod cli_output;
 mod dll_classifier;
 mod dll_management;
 mod dll_parser;
 mod game;
 mod gui;
+mod lang;
+mod logging;
+mod managed_backup;
 mod orirando;
+mod paths;
 mod self_update;
 mod settings;
+mod single_instance;
 mod steam;
-
-static LOGFILE: OnceLock<PathBuf> = OnceLock::new();
+#[cfg(test)]
+mod test_support;
 
 #[derive(Debug, Default)]
 struct Args {
     no_self_update_check: bool,
+    dry_run: bool,
+    classify: Vec<PathBuf>,
+    /// Install a locally present rando version and exit.
+    switch_version: Option<RandoVersion>,
+    /// Print the results of CLI commands as JSON instead of text.
+    json: bool,
+    /// Game dir for CLI commands, instead of the saved one.
+    game_dir: Option<PathBuf>,
 }
 
 fn main() {
-    let _logger_guard = setup();
+    let logger_guard = setup();
 
     let _span = info_span!("main").entered();
 
-    let args = match parse_args() {
+    let args_os: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let args = match parse_args(&args_os) {
         Ok(args) => {
             info!(?args, "Parsed CLI args");
             args
         }
+        Err(err) if is_cli_command(&args_os) => {
+            // Scripts have to notice that their command didn't run, instead of getting the gui
+            error!(?err, "Error parsing CLI args of a command");
+            if args_os.iter().any(|arg| arg == "--json") {
+                CliOutput::Error {
+                    message: format!("Invalid arguments: {err:#}"),
+                }
+                .print();
+            } else {
+                eprintln!("Invalid arguments: {err:#}");
+            }
+            exit_failure(logger_guard);
+        }
         Err(err) => {
             error!(?err, "Error parsing CLI args");
             Args::default()
         }
     };
 
+    // CLI commands run next to a running instance, they don't need the gui
+    let cli_success = if !args.classify.is_empty() {
+        Some(print_classifications(&args.classify, args.json))
+    } else if args.dry_run || args.switch_version.is_some() {
+        let settings = Settings::load();
+        let game_dir = cli_game_dir(&settings, args.game_dir.clone());
+        Some(match args.switch_version {
+            Some(version) => print_switch_version(&settings, &game_dir, version, args.json),
+            None => print_install_plan(&settings, &game_dir, args.json),
+        })
+    } else {
+        None
+    };
+    match cli_success {
+        Some(true) => return,
+        Some(false) => exit_failure(logger_guard),
+        None => {}
+    }
+
+    // A replacement spawned by self-update has to wait for the old instance to exit
+    let wait = if args.no_self_update_check {
+        Duration::from_secs(5)
+    } else {
+        Duration::ZERO
+    };
+    let Some(_instance_guard) = acquire_instance(wait) else {
+        info!("Another instance is already running, focusing it");
+        focus_existing_window();
+        return;
+    };
+
     let mut settings = Settings::load();
+    // A filter from the environment, e.g. for debugging, takes precedence over the saved log level
+    if std::env::var_os(EnvFilter::DEFAULT_ENV).is_none() {
+        set_log_level(settings.log_level);
+    }
 
-    if settings.game_dir.install.as_os_str().is_empty() || !verify_game_dir(&settings.game_dir) {
-        settings.game_dir = search_for_game_dir().unwrap_or_default();
-        settings.save_async();
+    if let Err(err) = settings.game_dir.check_reachable() {
+        // The share might just be offline for now, searching for another game dir would replace it for good
+        warn!(?err, "Game dir is unreachable, keeping it");
+    } else if !settings.game_dir.is_set() || !verify_game_dir(&settings.game_dir) {
+        // A missing game dir is kept if there's no other, the GUI warns about it
+        if let Some(game_dir) = search_for_game_dir() {
+            settings.game_dir = game_dir;
+            settings.save_async();
+        }
     }
 
+    let mut not_writable = None;
     if settings.self_update && !args.no_self_update_check {
         match self_update() {
             Ok(true) => {
@@ -73,12 +149,17 @@ fn main() {
             }
             Ok(false) => info!("Performed update check, no new version"),
             Err(err) => {
-                error!(?err, "Could not perform self-update");
+                error!(
+                    ?err,
+                    "Could not perform self-update, continuing with the current version"
+                );
+                // The user has to move the installer for updates to work, so they have to be told
+                not_writable = err.downcast_ref::<ExeDirNotWritable>().cloned();
             }
         }
     }
 
-    if let Err(e) = run_gui(settings) {
+    if let Err(e) = run_gui(settings, not_writable) {
         error!(?e, "Error running gui");
     }
 
@@ -92,33 +173,23 @@ fn setup() -> impl Any {
         .or_else(|_| EnvFilter::try_new("debug"))
         .unwrap();
 
-    let (stdout_writer, stdout_guard) = tracing_appender::non_blocking(io::stdout());
-    let stdout_logger = fmt::layer()
+    // Stdout is reserved for the output of CLI commands, e.g. `--json`, so logs never mix with it
+    let (stderr_writer, stderr_guard) = tracing_appender::non_blocking(io::stderr());
+    let stderr_logger = fmt::layer()
         .with_target(false)
         .with_ansi(colors)
-        .with_writer(stdout_writer);
-
-    let (file_logger, file_guard) = match create_log_file() {
-        Err(e) => {
-            eprintln!("Can't open log file: {e:?}");
-            (None, None)
-        }
-        Ok(file) => {
-            let (writer, guard) = tracing_appender::non_blocking(file);
-
-            let file_logger = fmt::layer()
-                .with_target(false)
-                .with_ansi(false)
-                .with_writer(writer);
+        .with_writer(stderr_writer);
 
-            (Some(file_logger), Some(guard))
-        }
-    };
+    let (file_writer, file_guard) = tracing_appender::non_blocking(LogFileWriter);
+    let file_logger = fmt::layer()
+        .with_target(false)
+        .with_ansi(false)
+        .with_writer(file_writer);
 
     tracing_subscriber::registry()
-        .with(filter_layer)
+        .with(reloadable_filter(filter_layer))
         .with(file_logger)
-        .with(stdout_logger)
+        .with(stderr_logger)
         .with(ErrorLayer::default())
         .init();
 
@@ -129,35 +200,224 @@ fn setup() -> impl Any {
         eprintln!("Error installing color_eyre hook: {e:?}");
     }
 
-    (stdout_guard, file_guard)
+    (stderr_guard, file_guard)
 }
 
-fn create_log_file() -> io::Result<File> {
-    let path = temp_dir().join("ori-de-randomizer.log");
-    let result = File::create(&path);
+/// Exits with code 1, for CLI commands that failed.
+fn exit_failure(logger_guard: impl Any) -> ! {
+    // Exiting skips destructors, but the buffered logs still have to be written
+    drop(logger_guard);
+    std::process::exit(1);
+}
 
-    if result.is_ok() {
-        _ = LOGFILE.set(path);
+/// The game dir CLI commands work on: the one passed with `--game-dir`, otherwise the saved or a detected one.
+/// Unlike the gui, CLI commands never change the saved game dir.
+#[instrument(skip(settings))]
+fn cli_game_dir(settings: &Settings, game_dir: Option<PathBuf>) -> GameDir {
+    if let Some(game_dir) = game_dir {
+        return GameDir::new(game_dir);
     }
 
-    result
+    // Network game dirs are probed by the commands themselves, which report an unreachable share
+    let saved = &settings.game_dir;
+    if saved.is_set() && (saved.is_network_path() || verify_game_dir(saved)) {
+        return saved.clone();
+    }
+    search_for_game_dir().unwrap_or_else(|| saved.clone())
 }
 
+/// Prints the classification of each file, or why it is invalid.
+/// Returns whether all files could be read.
 #[instrument]
-fn parse_args() -> Result<Args> {
-    debug!(args_os=?std::env::args_os().collect::<Vec<_>>(), "Parsing CLI args");
+fn print_classifications(paths: &[PathBuf], json: bool) -> bool {
+    let mut success = true;
+    for path in paths {
+        match std::fs::read(path) {
+            Ok(data) => match (classify_bytes(&data), json) {
+                (Ok(classification), true) => CliOutput::Classify {
+                    path: path.clone(),
+                    classification: Some(classification.into()),
+                    error: None,
+                }
+                .print(),
+                (Ok(classification), false) => println!("{}: {classification:?}", path.display()),
+                (Err(err), true) => CliOutput::Classify {
+                    path: path.clone(),
+                    classification: Some(DllClassification::Invalid.into()),
+                    error: Some(err.to_string()),
+                }
+                .print(),
+                (Err(err), false) => println!("{}: Invalid ({err})", path.display()),
+            },
+            Err(err) => {
+                error!(?path, ?err, "Couldn't read file to classify");
+                success = false;
+                if json {
+                    CliOutput::Classify {
+                        path: path.clone(),
+                        classification: None,
+                        error: Some(format!("Error reading file: {err}")),
+                    }
+                    .print();
+                } else {
+                    eprintln!("{}: Error reading file: {err}", path.display());
+                }
+            }
+        }
+    }
+    success
+}
+
+/// Prints what installing a dll would do, without touching any files.
+/// Returns whether the plan could be computed.
+#[instrument(skip_all)]
+fn print_install_plan(settings: &Settings, game_dir: &GameDir, json: bool) -> bool {
+    let plan = search_game_dir(game_dir).and_then(|(_current, all_dlls, _stray)| {
+        Ok(plan_install(game_dir, &all_dlls, settings.backup_location)?)
+    });
+
+    match plan {
+        Ok(plan) => {
+            info!(?plan, "Computed install plan");
+            if json {
+                CliOutput::dry_run(game_dir.install.clone(), plan).print();
+            } else {
+                println!("game_dir: {}", game_dir.install.display());
+                print!("{plan}");
+            }
+            true
+        }
+        Err(err) => {
+            error!(?err, "Error computing install plan");
+            if json {
+                CliOutput::Error {
+                    message: format!("Error computing install plan: {err:#}"),
+                }
+                .print();
+            } else {
+                eprintln!("Error computing install plan: {err:?}");
+            }
+            false
+        }
+    }
+}
+
+/// Installs `version` from the dlls in the Managed directory, like switching versions in the gui.
+#[instrument(skip(settings))]
+fn switch_version(
+    settings: &Settings,
+    game_dir: &GameDir,
+    version: RandoVersion,
+) -> Result<InstallPlan> {
+    let (_current, all_dlls, _stray) = search_game_dir(game_dir)?;
+    let to_install = all_dlls
+        .iter()
+        .find(|dll| dll.kind == OriDllKind::Rando(version))
+        .ok_or_else(|| eyre!("Version {version} isn't present in the Managed directory"))?;
+
+    let plan = install_dll(game_dir, to_install, &all_dlls, settings.backup_location)?;
+    if let Err(err) = prune_backups(game_dir, settings.max_backups) {
+        warn!(?err, "Couldn't prune old backups");
+    }
+    Ok(plan)
+}
+
+/// Switches to `version` and prints the result.
+/// Returns whether the switch succeeded.
+#[instrument(skip(settings))]
+fn print_switch_version(
+    settings: &Settings,
+    game_dir: &GameDir,
+    version: RandoVersion,
+    json: bool,
+) -> bool {
+    match switch_version(settings, game_dir, version) {
+        Ok(plan) => {
+            info!(?plan, "Switched version");
+            if json {
+                CliOutput::SwitchVersion {
+                    version: version.to_string(),
+                    target: plan.target,
+                    backup: plan.backup,
+                }
+                .print();
+            } else {
+                println!("Switched to {version}");
+                if let Some(backup) = &plan.backup {
+                    println!("backup: {}", backup.display());
+                }
+            }
+            true
+        }
+        Err(err) => {
+            error!(?err, "Error switching version");
+            if json {
+                CliOutput::Error {
+                    message: format!("Error switching version: {err:#}"),
+                }
+                .print();
+            } else {
+                eprintln!("Error switching version: {err:?}");
+            }
+            false
+        }
+    }
+}
 
+/// Flags that only make sense for CLI commands, as opposed to flags of the gui like `--no-self-update-check`.
+const CLI_COMMAND_FLAGS: &[&str] = &[
+    "--classify",
+    "--dry-run",
+    "--switch-version",
+    "--json",
+    "--game-dir",
+];
+
+/// Whether `args_os` asks for a CLI command, even if they can't be parsed.
+fn is_cli_command(args_os: &[OsString]) -> bool {
+    args_os
+        .iter()
+        .any(|arg| CLI_COMMAND_FLAGS.iter().any(|flag| arg == flag))
+}
+
+/// Parses the args without argv[0].
+#[instrument]
+fn parse_args(args_os: &[OsString]) -> Result<Args> {
     let mut args = Args::default();
 
-    // Skip argv[0]
-    for arg in std::env::args_os().skip(1) {
+    let mut args_os = args_os.iter().cloned();
+    while let Some(arg) = args_os.next() {
         if arg == "--no-self-update-check" {
             args.no_self_update_check = true;
+        } else if arg == "--json" {
+            args.json = true;
+        } else if arg == "--dry-run" {
+            args.dry_run = true;
+        } else if arg == "--game-dir" {
+            let path = args_os.next().ok_or_eyre("Missing path after --game-dir")?;
+            args.game_dir = Some(path.into());
+        } else if arg == "--classify" {
+            let path = args_os.next().ok_or_eyre("Missing file after --classify")?;
+            args.classify.push(path.into());
+        } else if arg == "--switch-version" {
+            let version = args_os
+                .next()
+                .ok_or_eyre("Missing version after --switch-version")?;
+            let version = version
+                .to_str()
+                .and_then(RandoVersion::parse)
+                .ok_or_else(|| eyre!("Invalid version {version:?}, expected x.y.z"))?;
+            args.switch_version = Some(version);
         } else {
             bail!("Unexpected argument {arg:?}");
         }
     }
 
+    // Switching always installs, so a dry run of it would silently modify the game
+    if args.dry_run && args.switch_version.is_some() {
+        bail!("--dry-run can't be combined with --switch-version");
+    }
+
     Ok(args)
 }
 
@@ -207,3 +467,62 @@ fn try_drop() {
     // (I couldn't find any documentation on that though)
     // unsafe { HeapFree(heap, 0, pointer) };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parses_switch_version() {
+        let parsed = parse_args(&args(&["--switch-version", "4.1.0", "--json"])).unwrap();
+
+        assert_eq!(
+            parsed.switch_version,
+            Some(RandoVersion {
+                major: 4,
+                minor: 1,
+                patch: 0,
+            })
+        );
+        assert!(parsed.json);
+        assert!(!parsed.dry_run);
+    }
+
+    #[test]
+    fn rejects_dry_run_of_switch_version() {
+        for order in [
+            ["--dry-run", "--switch-version", "4.1.0"],
+            ["--switch-version", "4.1.0", "--dry-run"],
+        ] {
+            let order = args(&order);
+            assert!(parse_args(&order).is_err());
+            assert!(is_cli_command(&order));
+        }
+    }
+
+    #[test]
+    fn invalid_cli_commands_are_still_cli_commands() {
+        for invalid in [
+            &["--switch-version"][..],
+            &["--switch-version", "latest"],
+            &["--json", "--switch-version", "4.1"],
+            &["--dry-run", "--unknown"],
+        ] {
+            let invalid = args(invalid);
+            assert!(parse_args(&invalid).is_err(), "{invalid:?}");
+            assert!(is_cli_command(&invalid), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn gui_args_are_no_cli_command() {
+        assert!(!is_cli_command(&args(&[])));
+        assert!(!is_cli_command(&args(&["--no-self-update-check"])));
+        assert!(!is_cli_command(&args(&["--unknown"])));
+        assert!(parse_args(&args(&["--no-self-update-check"])).is_ok());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::dll_management::lock_managed_dir;
+use crate::game::GameDir;
+use color_eyre::eyre::WrapErr;
+use color_eyre::{Result, Section, SectionExt};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Zips the whole Managed directory to `target`, so everything can be restored by hand.
+///
+/// Files are streamed into the archive one at a time, so memory use doesn't grow with the directory.
+/// `progress` is called with the number of files done and the total before each file.
+#[instrument(skip(game_dir, progress), fields(game_dir=?game_dir.install))]
+pub fn backup_managed_dir(
+    game_dir: &GameDir,
+    target: &Path,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<()> {
+    // Keeps installs from changing the directory while it's being zipped
+    let _lock = lock_managed_dir();
+    let managed = game_dir.resolved_managed();
+    let files = list_files(&managed).wrap_err("Error listing Managed directory")?;
+    info!(count = files.len(), "Backing up Managed directory");
+
+    let result = write_zip(&managed, &files, target, &mut progress);
+    if result.is_err() {
+        if let Err(err) = std::fs::remove_file(target) {
+            warn!(?err, "Couldn't remove incomplete backup");
+        }
+    }
+    result.with_section(|| format!("{target:?}").header("Backup file"))
+}
+
+fn write_zip(
+    managed: &Path,
+    files: &[PathBuf],
+    target: &Path,
+    progress: &mut impl FnMut(usize, usize),
+) -> Result<()> {
+    let file = File::create(target).wrap_err("Error creating backup file")?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+
+    for (done, path) in files.iter().enumerate() {
+        progress(done, files.len());
+
+        // Zip entries always use forward slashes
+        let name = path
+            .strip_prefix(managed)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        zip.start_file(name, options)
+            .wrap_err("Error adding file to backup")?;
+        let mut source = File::open(path)
+            .wrap_err("Error opening file")
+            .with_section(|| format!("{path:?}").header("File"))?;
+        std::io::copy(&mut source, &mut zip)
+            .wrap_err("Error writing file to backup")
+            .with_section(|| format!("{path:?}").header("File"))?;
+    }
+
+    let mut writer = zip.finish().wrap_err("Error finishing backup")?;
+    writer.flush().wrap_err("Error writing backup file")?;
+    Ok(())
+}
+
+/// All files below `dir`, sorted so backups of the same directory are laid out the same way.
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![dir.to_owned()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)
+            .wrap_err("Error reading directory")
+            .with_section(|| format!("{dir:?}").header("Directory"))?
+        {
+            let entry = entry.wrap_err("Error reading directory entry")?;
+            let file_type = entry.file_type().wrap_err("Error getting file type")?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
@@ -1,50 +1,528 @@
-use crate::dll_classifier::RandoVersion;
-use color_eyre::Result;
-use color_eyre::eyre::{OptionExt, WrapErr, bail};
+use crate::dll_classifier::{DllClassification, RandoVersion, classify_dll};
 use regex::Regex;
+use reqwest::StatusCode;
+use reqwest::header::{
+    CONTENT_TYPE, ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use serde::{Deserialize, Serialize};
+use std::num::{IntErrorKind, ParseIntError};
+use std::ops::RangeInclusive;
 use std::sync::LazyLock;
-use tracing::instrument;
+use tracing::{error, info, instrument, warn};
+
+pub const SITE_URL: &str = "https://orirando.com/";
+const DLL_URL: &str = "https://orirando.com/dll";
+
+/// Default for the download mirrors setting, in the order they are tried.
+pub const DEFAULT_DLL_MIRRORS: &[&str] = &[DLL_URL];
 
 static VERSION_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"<title>Ori DE Randomizer (\d+)\.(\d+)\.(\d+)</title>").unwrap());
 
+/// Why checking the version or downloading the dll failed.
+#[derive(Debug, thiserror::Error)]
+pub enum NetworkError {
+    #[error("Error accessing {url}")]
+    Request {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{url} did not return success: {status}")]
+    Status { url: String, status: StatusCode },
+    /// orirando.com returned something other than its regular page, e.g. a maintenance page or a Cloudflare challenge.
+    #[error("The randomizer site appears to be under maintenance: {reason}")]
+    SiteUnavailable { reason: String },
+    #[error("Failed to extract version from title")]
+    VersionNotFound,
+    #[error("Version number part {0:?} is too large")]
+    VersionTooLarge(String),
+    #[error("Failed to parse version number part {part:?}")]
+    InvalidVersion {
+        part: String,
+        #[source]
+        source: ParseIntError,
+    },
+    /// The download returned the vanilla dll, which means the server is misconfigured.
+    /// Installing it would silently revert the user to vanilla.
+    #[error("The server returned a vanilla DLL, not the randomizer")]
+    ServerReturnedVanilla,
+    #[error("Download has content type {0:?}, not a dll")]
+    UnexpectedContentType(String),
+    #[error("Download has {0} bytes, which is implausible for the game assembly")]
+    ImplausibleSize(u64),
+    #[error("Downloaded file is not a randomizer dll: {0:?}")]
+    NotRandoDll(DllClassification),
+    #[error("No download mirrors configured")]
+    NoMirrors,
+}
+
+/// Markers of maintenance and Cloudflare challenge pages, in lowercase.
+const UNAVAILABLE_MARKERS: &[&str] = &[
+    "maintenance",
+    "cloudflare",
+    "cf-browser-verification",
+    "challenge-platform",
+];
+
+/// Result of the last version check, with the validators to make the next check a conditional request.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct VersionCache {
+    pub version: RandoVersion,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Checks the newest version, `cache` is returned as is if the site hasn't changed since.
+pub fn check_version(cache: Option<&VersionCache>) -> Result<VersionCache, NetworkError> {
+    check_version_at(SITE_URL, cache)
+}
+
+/// Like [`check_version`], but against the site at `url`, e.g. a local test server.
 #[instrument]
-pub fn check_version() -> Result<RandoVersion> {
-    let resp =
-        reqwest::blocking::get("https://orirando.com/").wrap_err("Error accessing orirando.com")?;
+pub fn check_version_at(
+    url: &str,
+    cache: Option<&VersionCache>,
+) -> Result<VersionCache, NetworkError> {
+    let mut request = reqwest::blocking::Client::new().get(url);
+    if let Some(cache) = cache {
+        if let Some(etag) = &cache.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cache.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let request_error = |source| NetworkError::Request {
+        url: url.to_owned(),
+        source,
+    };
+    let resp = request.send().map_err(request_error)?;
+
+    if let (StatusCode::NOT_MODIFIED, Some(cache)) = (resp.status(), cache) {
+        info!(?cache.version, "Site not modified, using cached version");
+        return Ok(cache.clone());
+    }
 
     if !resp.status().is_success() {
-        bail!("orirando.com did not return success: {}", resp.status());
+        return Err(NetworkError::Status {
+            url: url.to_owned(),
+            status: resp.status(),
+        });
     }
 
-    let html = resp.text().wrap_err("Error getting text of orirando.com")?;
+    // Without a content type the page is still checked for the version below
+    if let Some(content_type) = resp.headers().get(CONTENT_TYPE) {
+        let content_type = String::from_utf8_lossy(content_type.as_bytes());
+        if !content_type.to_ascii_lowercase().starts_with("text/html") {
+            return Err(NetworkError::SiteUnavailable {
+                reason: format!("Unexpected content type {content_type:?}"),
+            });
+        }
+    }
+
+    let header = |name: HeaderName| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+
+    let html = resp.text().map_err(request_error)?;
 
-    let captures = VERSION_REGEX
-        .captures(&html)
-        .ok_or_eyre("Failed to extract version from title")?;
+    let Some(captures) = VERSION_REGEX.captures(&html) else {
+        let lowercase_html = html.to_lowercase();
+        let marker = UNAVAILABLE_MARKERS
+            .iter()
+            .find(|marker| lowercase_html.contains(*marker));
+        return Err(match marker {
+            Some(marker) => NetworkError::SiteUnavailable {
+                reason: format!("Page contains {marker:?}"),
+            },
+            None => NetworkError::VersionNotFound,
+        });
+    };
     let (_full, [major, minor, patch]) = captures.extract();
 
-    Ok(RandoVersion {
+    let version = RandoVersion {
         major: parse_version_number_part(major)?,
         minor: parse_version_number_part(minor)?,
         patch: parse_version_number_part(patch)?,
+    };
+
+    Ok(VersionCache {
+        version,
+        etag,
+        last_modified,
     })
 }
 
-fn parse_version_number_part(num: &str) -> Result<u32> {
-    num.parse().wrap_err("Failed to parse version number part")
+/// Parses into the type of the [`RandoVersion`] fields.
+/// The regex only captures digits, so a number that doesn't fit is the only likely failure.
+fn parse_version_number_part(num: &str) -> Result<u32, NetworkError> {
+    num.parse().map_err(|err: ParseIntError| match err.kind() {
+        IntErrorKind::PosOverflow => NetworkError::VersionTooLarge(num.to_owned()),
+        _ => NetworkError::InvalidVersion {
+            part: num.to_owned(),
+            source: err,
+        },
+    })
 }
 
+/// Release date of the newest dll, taken from the `Last-Modified` header of the download.
+/// This is informational only, so errors are logged and `None` is returned.
+pub fn check_release_date() -> Option<String> {
+    check_release_date_at(DLL_URL)
+}
+
+/// Like [`check_release_date`], but of the download at `url`, e.g. on a local test server.
 #[instrument]
-pub fn download_dll() -> Result<Vec<u8>> {
-    let resp = reqwest::blocking::get("https://orirando.com/dll")
-        .wrap_err("Error accessing orirando.com")?;
+fn check_release_date_at(url: &str) -> Option<String> {
+    // Error pages have a `Last-Modified` of their own, which isn't the release date
+    let resp = reqwest::blocking::Client::new()
+        .head(url)
+        .send()
+        .and_then(reqwest::blocking::Response::error_for_status);
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(err) => {
+            warn!(?err, "Couldn't check release date");
+            return None;
+        }
+    };
+
+    let last_modified = resp.headers().get(LAST_MODIFIED)?.to_str().ok()?;
+
+    // IMF-fixdate, e.g. "Sun, 06 Nov 1994 08:49:37 GMT". Only the date is of interest.
+    let date = last_modified
+        .split_whitespace()
+        .skip(1)
+        .take(3)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (!date.is_empty()).then_some(date)
+}
+
+/// Tries each mirror in order, until one returns a randomizer dll.
+/// If all of them fail, the error of the last one is returned.
+#[instrument]
+pub fn download_dll(mirrors: &[String]) -> Result<Vec<u8>, NetworkError> {
+    let mut last_error = None;
+
+    for url in mirrors {
+        match download_dll_from(url) {
+            Ok(dll) => {
+                info!(?url, "Downloaded dll");
+                return Ok(dll);
+            }
+            Err(err) => {
+                warn!(?url, ?err, "Error downloading dll from mirror");
+                last_error = Some(err);
+            }
+        }
+    }
+
+    error!("Failed to download dll from all mirrors");
+    Err(last_error.unwrap_or(NetworkError::NoMirrors))
+}
+
+#[instrument]
+fn download_dll_from(url: &str) -> Result<Vec<u8>, NetworkError> {
+    let request_error = |source| NetworkError::Request {
+        url: url.to_owned(),
+        source,
+    };
+    let resp = reqwest::blocking::get(url).map_err(request_error)?;
 
     if !resp.status().is_success() {
-        bail!("orirando.com did not return success: {}", resp.status());
+        return Err(NetworkError::Status {
+            url: url.to_owned(),
+            status: resp.status(),
+        });
+    }
+
+    // Cheap checks first, error pages are caught before downloading and classifying them
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    if let Some(content_type) =
+        content_type.filter(|content_type| is_text_content_type(content_type))
+    {
+        return Err(NetworkError::UnexpectedContentType(content_type.to_owned()));
     }
+    if let Some(size) = resp.content_length() {
+        check_dll_size(size)?;
+    }
+
+    let bytes = resp.bytes().map_err(request_error)?;
+    check_dll_size(bytes.len() as u64)?;
 
-    let bytes = resp.bytes().wrap_err("Error downloading dll")?;
+    // Don't install whatever a broken mirror returns
+    let classification = classify_dll(&bytes);
+    if classification == DllClassification::Vanilla {
+        error!(
+            ?url,
+            "Server returned the vanilla dll, this should be reported"
+        );
+        return Err(NetworkError::ServerReturnedVanilla);
+    }
+    if !matches!(
+        classification,
+        DllClassification::Rando(_) | DllClassification::UnknownRando(_)
+    ) {
+        return Err(NetworkError::NotRandoDll(classification));
+    }
 
     Ok(bytes.to_vec())
 }
+
+/// Servers send error pages as text, while dlls are sent as e.g. `application/octet-stream`.
+fn is_text_content_type(content_type: &str) -> bool {
+    let content_type = content_type.to_ascii_lowercase();
+    content_type.starts_with("text/") || content_type.starts_with("application/json")
+}
+
+/// Plausible sizes of the game assembly. The vanilla one has about 3 MB.
+const DLL_SIZE_RANGE: RangeInclusive<u64> = 512 * 1024..=64 * 1024 * 1024;
+
+fn check_dll_size(size: u64) -> Result<(), NetworkError> {
+    if DLL_SIZE_RANGE.contains(&size) {
+        Ok(())
+    } else {
+        Err(NetworkError::ImplausibleSize(size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Response, TestServer, rando_dll, unreachable_url, vanilla_dll};
+
+    const VERSION: RandoVersion = RandoVersion {
+        major: 4,
+        minor: 1,
+        patch: 0,
+    };
+
+    fn site_page(title: &str) -> Response {
+        Response::new(
+            200,
+            "text/html; charset=utf-8",
+            format!("<html><head><title>{title}</title></head></html>"),
+        )
+    }
+
+    fn dll_response(dll: Vec<u8>) -> Response {
+        Response::new(200, "application/octet-stream", dll)
+    }
+
+    #[test]
+    fn checks_version() {
+        let server = TestServer::start([(
+            "/",
+            site_page("Ori DE Randomizer 4.1.0")
+                .with_header("ETag", "\"abc\"")
+                .with_header("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        )]);
+
+        let cache = check_version_at(&server.url("/"), None).unwrap();
+
+        assert_eq!(
+            cache,
+            VersionCache {
+                version: VERSION,
+                etag: Some("\"abc\"".to_owned()),
+                last_modified: Some("Sun, 06 Nov 1994 08:49:37 GMT".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn checks_version_with_uppercase_or_missing_content_type() {
+        let page = site_page("Ori DE Randomizer 4.1.0");
+        let server = TestServer::start([
+            (
+                "/uppercase",
+                Response::new(200, "TEXT/HTML; charset=UTF-8", page.body.clone()),
+            ),
+            (
+                "/missing",
+                Response {
+                    headers: Vec::new(),
+                    ..page
+                },
+            ),
+        ]);
+
+        for path in ["/uppercase", "/missing"] {
+            let cache = check_version_at(&server.url(path), None).unwrap();
+            assert_eq!(cache.version, VERSION, "{path}");
+        }
+    }
+
+    #[test]
+    fn uses_cache_if_not_modified() {
+        let server = TestServer::start([("/", Response::new(304, "text/html", ""))]);
+        let cache = VersionCache {
+            version: VERSION,
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: Some("Sun, 06 Nov 1994 08:49:37 GMT".to_owned()),
+        };
+
+        assert_eq!(
+            check_version_at(&server.url("/"), Some(&cache)).unwrap(),
+            cache
+        );
+
+        let request = &server.requests()[0];
+        assert_eq!(request.headers["if-none-match"], "\"abc\"");
+        assert_eq!(
+            request.headers["if-modified-since"],
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+    }
+
+    #[test]
+    fn version_check_errors() {
+        let server = TestServer::start([
+            ("/unavailable", Response::new(503, "text/html", "")),
+            ("/maintenance", site_page("Down for maintenance, back soon")),
+            ("/json", Response::new(200, "application/json", "{}")),
+            ("/other", site_page("Some other site")),
+            ("/huge", site_page("Ori DE Randomizer 4.99999999999.0")),
+        ]);
+        let check = |path| check_version_at(&server.url(path), None).unwrap_err();
+
+        assert!(matches!(
+            check("/unavailable"),
+            NetworkError::Status {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+        assert!(matches!(
+            check("/maintenance"),
+            NetworkError::SiteUnavailable { .. }
+        ));
+        assert!(matches!(
+            check("/json"),
+            NetworkError::SiteUnavailable { .. }
+        ));
+        assert!(matches!(check("/other"), NetworkError::VersionNotFound));
+        assert!(
+            matches!(check("/huge"), NetworkError::VersionTooLarge(part) if part == "99999999999")
+        );
+        assert!(matches!(
+            check_version_at(&unreachable_url(), None).unwrap_err(),
+            NetworkError::Request { .. }
+        ));
+    }
+
+    #[test]
+    fn downloads_rando_dll() {
+        let dll = rando_dll("4.1.0");
+        let server = TestServer::start([("/dll", dll_response(dll.clone()))]);
+
+        assert_eq!(download_dll(&[server.url("/dll")]).unwrap(), dll);
+    }
+
+    #[test]
+    fn falls_back_to_next_mirror() {
+        let dll = rando_dll("4.1.0");
+        let server = TestServer::start([
+            ("/unavailable", Response::new(503, "text/html", "")),
+            ("/dll", dll_response(dll.clone())),
+        ]);
+        let mirrors = [
+            unreachable_url(),
+            server.url("/unavailable"),
+            server.url("/dll"),
+        ];
+
+        assert_eq!(download_dll(&mirrors).unwrap(), dll);
+
+        let requests = server.requests();
+        let paths: Vec<_> = requests.iter().map(|request| &request.path).collect();
+        assert_eq!(paths, ["/unavailable", "/dll"]);
+        assert!(requests.iter().all(|request| request.method == "GET"));
+    }
+
+    #[test]
+    fn download_errors() {
+        let server = TestServer::start([
+            ("/unavailable", Response::new(503, "text/html", "")),
+            ("/page", site_page("Ori DE Randomizer 4.1.0")),
+            ("/small", dll_response(vec![0; 1024])),
+            ("/vanilla", dll_response(vanilla_dll())),
+            ("/invalid", dll_response(vec![0; 1024 * 1024])),
+        ]);
+        let download = |path| download_dll(&[server.url(path)]).unwrap_err();
+
+        assert!(matches!(
+            download("/unavailable"),
+            NetworkError::Status {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+        assert!(matches!(
+            download("/page"),
+            NetworkError::UnexpectedContentType(_)
+        ));
+        assert!(matches!(
+            download("/small"),
+            NetworkError::ImplausibleSize(1024)
+        ));
+        assert!(matches!(
+            download("/vanilla"),
+            NetworkError::ServerReturnedVanilla
+        ));
+        assert!(matches!(
+            download("/invalid"),
+            NetworkError::NotRandoDll(DllClassification::Invalid)
+        ));
+        assert!(matches!(
+            download_dll(&[unreachable_url()]).unwrap_err(),
+            NetworkError::Request { .. }
+        ));
+        assert!(matches!(
+            download_dll(&[]).unwrap_err(),
+            NetworkError::NoMirrors
+        ));
+    }
+
+    #[test]
+    fn checks_release_date() {
+        let server = TestServer::start([(
+            "/dll",
+            dll_response(Vec::new()).with_header("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        )]);
+
+        assert_eq!(
+            check_release_date_at(&server.url("/dll")).as_deref(),
+            Some("06 Nov 1994")
+        );
+        assert_eq!(server.requests()[0].method, "HEAD");
+    }
+
+    #[test]
+    fn no_release_date_of_error_page() {
+        let server = TestServer::start([
+            (
+                "/unavailable",
+                Response::new(503, "text/html", "")
+                    .with_header("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ),
+            ("/undated", dll_response(Vec::new())),
+        ]);
+
+        assert_eq!(check_release_date_at(&server.url("/unavailable")), None);
+        assert_eq!(check_release_date_at(&server.url("/undated")), None);
+        assert_eq!(check_release_date_at(&unreachable_url()), None);
+    }
+}
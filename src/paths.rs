@@ -0,0 +1,119 @@
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf, Prefix};
+
+/// Adds the `\\?\` prefix to absolute drive paths, which lifts the `MAX_PATH` limit of the Windows API.
+/// Paths with that prefix aren't normalized by Windows anymore, so this normalizes them beforehand.
+/// Other paths, e.g. relative or network paths, are returned unchanged.
+///
+/// Only raw Windows API calls need this, `std::fs` adds the prefix to long paths by itself.
+/// The prefixed path shouldn't be shown or logged, users don't know paths like that.
+pub fn extended_length_path(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_owned();
+    };
+    if !matches!(prefix.kind(), Prefix::Disk(_)) || !path.is_absolute() {
+        return path.to_owned();
+    }
+
+    let mut extended = OsString::from(r"\\?\");
+    extended.push(prefix.as_os_str());
+
+    let mut rest = PathBuf::new();
+    for component in components {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                rest.pop();
+            }
+            component => rest.push(component),
+        }
+    }
+    extended.push(rest);
+
+    extended.into()
+}
+
+/// Removes the `\\?\` prefix of drive and network paths, e.g. of canonicalized paths, so they look like users know them.
+/// Other verbatim paths, e.g. of volume GUIDs, have no plain form and are returned unchanged.
+pub fn without_verbatim_prefix(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_owned();
+    };
+
+    let mut plain = match prefix.kind() {
+        Prefix::VerbatimDisk(letter) => PathBuf::from(format!("{}:", char::from(letter))),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut unc = OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            PathBuf::from(unc)
+        }
+        _ => return path.to_owned(),
+    };
+    // Pushing the root dir keeps the prefix, e.g. `C:` becomes `C:\`
+    plain.extend(components);
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Longer than `MAX_PATH`, like a game dir in a deeply nested Steam library.
+    fn long_path() -> PathBuf {
+        let mut path = PathBuf::from(r"C:\");
+        for _ in 0..6 {
+            path.push("a".repeat(50));
+        }
+        path.push(r"Ori DE\oriDE_Data\Managed\Assembly-CSharp.dll");
+        assert!(path.as_os_str().len() > 260);
+        path
+    }
+
+    #[test]
+    fn extends_long_path() {
+        let path = long_path();
+        let extended = extended_length_path(&path);
+
+        let expected = format!(r"\\?\{}", path.display());
+        assert_eq!(extended, PathBuf::from(expected));
+        assert_eq!(without_verbatim_prefix(&extended), path);
+    }
+
+    #[test]
+    fn normalizes_before_extending() {
+        assert_eq!(
+            extended_length_path(Path::new(r"C:\Games\.\Steam\..\Ori DE")),
+            PathBuf::from(r"\\?\C:\Games\Ori DE")
+        );
+    }
+
+    #[test]
+    fn only_extends_absolute_drive_paths() {
+        for path in [r"Games\Ori DE", r"C:Games\Ori DE", r"\\server\share\Ori DE"] {
+            assert_eq!(extended_length_path(Path::new(path)), Path::new(path));
+        }
+    }
+
+    #[test]
+    fn strips_verbatim_prefix() {
+        assert_eq!(
+            without_verbatim_prefix(Path::new(r"\\?\C:\Games\Ori DE")),
+            PathBuf::from(r"C:\Games\Ori DE")
+        );
+        assert_eq!(
+            without_verbatim_prefix(Path::new(r"\\?\UNC\server\share\Ori DE")),
+            PathBuf::from(r"\\server\share\Ori DE")
+        );
+        for path in [
+            r"C:\Games\Ori DE",
+            r"\\server\share\Ori DE",
+            r"\\?\Volume{01234567-89ab-cdef-0123-456789abcdef}\Ori DE",
+        ] {
+            assert_eq!(without_verbatim_prefix(Path::new(path)), Path::new(path));
+        }
+    }
+}
@@ -1,10 +1,18 @@
-use color_eyre::eyre::{OptionExt, WrapErr, bail};
-use color_eyre::{Result, Section, SectionExt};
+use crate::dll_classifier::sha256_file;
+use crate::paths::without_verbatim_prefix;
+use color_eyre::eyre::{OptionExt, WrapErr, bail, eyre};
+use color_eyre::{Report, Result, Section, SectionExt};
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use reqwest::header::RANGE;
 use serde::{Deserialize, Serialize};
-use std::io::ErrorKind;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use tracing::{debug, info, instrument};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, instrument, warn};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct LatestReleaseResponse {
@@ -12,29 +20,44 @@ struct LatestReleaseResponse {
     assets: Vec<ReleaseAsset>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct ReleaseAsset {
     name: String,
     browser_download_url: String,
+    size: u64,
+    /// Checksum like "sha256:<hex>", GitHub only has it for assets uploaded after it started computing them.
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// The exe of a release that is newer than the running version.
+#[derive(Debug, Clone)]
+struct NewVersion {
+    tag: String,
+    asset: ReleaseAsset,
 }
 
 #[instrument]
 pub fn self_update() -> Result<bool> {
-    let Some(url) = new_version_url().wrap_err("Error fetching new version")? else {
+    let Some(new_version) = new_version_asset().wrap_err("Error fetching new version")? else {
         return Ok(false);
     };
 
-    info!(?url, "Installing new app version");
+    info!(?new_version, "Installing new app version");
 
-    let new_version = download_new_version(url).wrap_err("Error downloading new version")?;
+    let downloaded =
+        download_new_version(&new_version).wrap_err("Error downloading new version")?;
 
     let current_file = prepare_target_file().wrap_err("Error preparing target file")?;
 
-    std::fs::write(&current_file, new_version).wrap_err("Failed to write new version")?;
+    std::fs::copy(&downloaded, &current_file).wrap_err("Failed to write new version")?;
+    if let Err(err) = std::fs::remove_file(&downloaded) {
+        warn!(?err, ?downloaded, "Couldn't remove downloaded file");
+    }
 
     info!(?current_file, "New version written, spawning replacement");
 
-    Command::new(current_file)
+    let mut child = Command::new(&current_file)
         .arg("--no-self-update-check")
         .args(std::env::args_os().skip(1)) // skip argv[0]
         .stdin(Stdio::piped())
@@ -42,18 +65,77 @@ pub fn self_update() -> Result<bool> {
         .spawn()
         .wrap_err("Failed to spawn replacement process")?;
 
+    // Exiting now would leave nothing running if the new version is broken
+    if let Err(err) = check_replacement_started(&mut child) {
+        error!(
+            ?err,
+            "Replacement didn't start, restoring the current version"
+        );
+        let err = err.wrap_err("Replacement process failed to start");
+        return match restore_old_version(&current_file) {
+            Ok(()) => Err(err),
+            Err(restore_err) => {
+                Err(err).with_section(|| format!("{restore_err:?}").header("Restore error"))
+            }
+        };
+    }
+
     Ok(true)
 }
 
+/// A replacement that exits this soon after being spawned is considered broken.
+const STARTUP_CHECK_DURATION: Duration = Duration::from_secs(2);
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Waits a moment to catch replacements that crash right away, e.g. because of a broken download.
+/// A working replacement waits for this instance to exit, so it's still running afterwards.
+#[instrument(skip(child), fields(pid = child.id()))]
+fn check_replacement_started(child: &mut Child) -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < STARTUP_CHECK_DURATION {
+        if let Some(status) = child
+            .try_wait()
+            .wrap_err("Failed to check replacement process")?
+        {
+            bail!("Replacement process exited right after starting with {status}");
+        }
+        thread::sleep(STARTUP_POLL_INTERVAL);
+    }
+    debug!("Replacement process is running");
+    Ok(())
+}
+
+/// Moves the current version back in place of a broken replacement.
+#[instrument]
+fn restore_old_version(current_file: &Path) -> Result<()> {
+    let old_file = old_file_path(current_file)?;
+    std::fs::remove_file(current_file)
+        .wrap_err("Failed to delete new version")
+        .with_section(|| format!("{current_file:?}").header("File path"))?;
+    std::fs::rename(&old_file, current_file)
+        .wrap_err("Failed to restore old version")
+        .with_section(|| format!("{old_file:?}").header("Old file"))?;
+    info!(?current_file, "Restored old version");
+    Ok(())
+}
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/ori-community/bf-rando-installer/releases/latest";
+
+fn new_version_asset() -> Result<Option<NewVersion>> {
+    new_version_asset_from(LATEST_RELEASE_URL)
+}
+
+/// Like [`new_version_asset`], but queries the latest release from `api_url`, e.g. a local test server.
 #[instrument]
-fn new_version_url() -> Result<Option<String>> {
+fn new_version_asset_from(api_url: &str) -> Result<Option<NewVersion>> {
     let client = reqwest::blocking::Client::builder()
         .user_agent("ori-de-randomizer")
         .build()
         .wrap_err("Cannot create client")?;
 
     let resp = client
-        .get("https://api.github.com/repos/ori-community/bf-rando-installer/releases/latest")
+        .get(api_url)
         .header("Accept", "application/vnd.github+json")
         .send()
         .wrap_err("Could not query github API")?;
@@ -66,15 +148,10 @@ fn new_version_url() -> Result<Option<String>> {
     let payload: LatestReleaseResponse =
         serde_json::from_str(&payload).wrap_err("Invalid response json")?;
 
-    let version_string = payload
-        .tag_name
-        .strip_prefix('v')
-        .unwrap_or(&payload.tag_name);
-
-    let current_version: Vec<u32> = parse_version_string(env!("CARGO_PKG_VERSION"))
+    let current_version = parse_version_string(env!("CARGO_PKG_VERSION"))
         .wrap_err("Failed to parse current version")?;
     let new_version =
-        parse_version_string(version_string).wrap_err("Failed to parse new version string")?;
+        parse_version_string(&payload.tag_name).wrap_err("Failed to parse new version string")?;
 
     debug!(?current_version, ?new_version, "Fetched app versions");
 
@@ -82,42 +159,299 @@ fn new_version_url() -> Result<Option<String>> {
         return Ok(None);
     }
 
-    for asset in payload.assets {
-        if std::path::Path::new(&asset.name)
+    let current_exe = std::env::current_exe().ok();
+    let current_stem = current_exe
+        .as_deref()
+        .and_then(Path::file_stem)
+        .map(|stem| stem.to_string_lossy());
+
+    let asset = pick_exe_asset(&payload.assets, current_stem.as_deref())
+        .ok_or_eyre("No exe asset in release")?;
+    debug!(?asset, "Picked release asset");
+
+    Ok(Some(NewVersion {
+        tag: payload.tag_name.clone(),
+        asset: asset.clone(),
+    }))
+}
+
+/// Releases might contain several exes, e.g. per-arch builds.
+/// The one named like the running exe is preferred, otherwise the first exe is used.
+fn pick_exe_asset<'a>(
+    assets: &'a [ReleaseAsset],
+    current_stem: Option<&str>,
+) -> Option<&'a ReleaseAsset> {
+    let mut exes = assets.iter().filter(|asset| {
+        Path::new(&asset.name)
             .extension()
             .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
-        {
-            return Ok(Some(asset.browser_download_url));
-        }
-    }
+    });
+
+    let matching = current_stem.and_then(|current_stem| {
+        exes.clone().find(|asset| {
+            Path::new(&asset.name)
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(current_stem))
+        })
+    });
 
-    bail!("No exe asset in release")
+    matching.or_else(|| exes.next())
 }
 
+/// Normalized app version, ordered by its numbers first. A pre-release is older than the release of the same numbers.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct AppVersion {
+    numbers: Vec<u32>,
+    is_release: bool,
+}
+
+/// Parses versions like "1.2", "1.2.3-rc1" or "1.2.3.4", optionally prefixed with "v" like release tags.
+/// Missing parts count as 0, so "1.2" and "1.2.0" are the same version.
 #[instrument]
-fn parse_version_string(version_string: &str) -> Result<Vec<u32>> {
-    version_string
+fn parse_version_string(version_string: &str) -> Result<AppVersion> {
+    let version_string = version_string.strip_prefix('v').unwrap_or(version_string);
+    // Build metadata doesn't affect the order
+    let version_string = version_string
+        .split_once('+')
+        .map_or(version_string, |(version, _build)| version);
+    let (numbers, pre_release) = match version_string.split_once('-') {
+        Some((numbers, pre_release)) => (numbers, Some(pre_release)),
+        None => (version_string, None),
+    };
+
+    let mut numbers = numbers
         .split('.')
         .map(str::parse)
-        .collect::<Result<_, _>>()
-        .wrap_err("Failed to parse version string")
+        .collect::<Result<Vec<u32>, _>>()
+        .wrap_err("Failed to parse version string")?;
+    numbers.resize(numbers.len().max(3), 0);
+    while numbers.len() > 3 && numbers.last() == Some(&0) {
+        numbers.pop();
+    }
+
+    Ok(AppVersion {
+        numbers,
+        is_release: pre_release.is_none(),
+    })
 }
 
-#[instrument]
-fn download_new_version(url: String) -> Result<impl AsRef<[u8]>> {
-    let resp = reqwest::blocking::get(url).wrap_err("Could not fetch new version")?;
+/// The update is skipped if downloading takes longer than this, so startup isn't blocked indefinitely.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(1);
+/// Interrupted downloads are resumed where they stopped, up to this many times.
+const DOWNLOAD_ATTEMPTS: u32 = 5;
 
-    if !resp.status().is_success() {
-        bail!("Non-success status code {}", resp.status());
+/// Downloads the exe of `new_version` to a temporary file and returns its path once its checksum matches.
+/// The file is kept if the download fails, so the next attempt, even of a later run, can resume it.
+#[instrument(skip(new_version), fields(url = ?new_version.asset.browser_download_url))]
+fn download_new_version(new_version: &NewVersion) -> Result<PathBuf> {
+    let asset = &new_version.asset;
+    let sha256 = expected_sha256(asset)?;
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent("ori-de-randomizer")
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .wrap_err("Cannot create client")?;
+
+    let path = partial_download_path(new_version);
+    // Earlier versions didn't key the file by release, so it might belong to any of them
+    let unkeyed_path = std::env::temp_dir().join(format!("{}.part", asset.name));
+    remove_download_file(&unkeyed_path);
+
+    let started = Instant::now();
+
+    let mut last_error = None;
+    for attempt in 1..=DOWNLOAD_ATTEMPTS {
+        match download_to_file(&client, asset, &path, sha256) {
+            Ok(()) => return Ok(path),
+            Err(err) => {
+                warn!(attempt, ?err, "Download interrupted");
+                last_error = Some(err);
+            }
+        }
+
+        if started.elapsed() >= DOWNLOAD_TIMEOUT {
+            break;
+        }
+        thread::sleep(Duration::from_secs(attempt.into()));
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| eyre!("No download attempts"))
+        .wrap_err("Download failed repeatedly"))
+}
+
+/// The SHA-256 GitHub computed for `asset`, as hex.
+/// Without one a corrupted or tampered download can't be told apart, so the update is skipped.
+fn expected_sha256(asset: &ReleaseAsset) -> Result<&str> {
+    let digest = asset
+        .digest
+        .as_deref()
+        .ok_or_eyre("The release has no checksum for the exe")?;
+    digest
+        .strip_prefix("sha256:")
+        .filter(|hex| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or_else(|| eyre!("Unsupported checksum {digest:?}"))
+}
+
+/// A partial download is only resumed by the same release asset, so the file name contains the tag and the size.
+fn partial_download_path(new_version: &NewVersion) -> PathBuf {
+    let tag: String = new_version
+        .tag
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let asset = &new_version.asset;
+    std::env::temp_dir().join(format!("{}.{tag}.{}.part", asset.name, asset.size))
+}
+
+fn remove_download_file(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => info!(?path, "Removed download file"),
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => warn!(?err, ?path, "Couldn't remove download file"),
+    }
+}
+
+/// Continues the download into `path` from wherever the previous attempt stopped.
+/// Only succeeds once the file has the size announced by the release and the expected checksum.
+/// Otherwise the file is deleted, so the next attempt starts over.
+fn download_to_file(
+    client: &Client,
+    asset: &ReleaseAsset,
+    path: &Path,
+    expected_sha256: &str,
+) -> Result<()> {
+    let mut file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err("Could not open download file")
+        .with_section(|| format!("{path:?}").header("File path"))?;
+    let mut downloaded = file
+        .metadata()
+        .wrap_err("Could not read download file")?
+        .len();
+
+    if downloaded > asset.size {
+        info!(
+            downloaded,
+            "Download file is larger than expected, starting over"
+        );
+        file.set_len(0)
+            .wrap_err("Could not truncate download file")?;
+        downloaded = 0;
+    }
+
+    if downloaded < asset.size {
+        let mut request = client.get(&asset.browser_download_url);
+        if downloaded > 0 {
+            info!(downloaded, "Resuming download");
+            request = request.header(RANGE, format!("bytes={downloaded}-"));
+        }
+        let mut resp = request.send().wrap_err("Could not fetch new version")?;
+
+        match resp.status() {
+            StatusCode::PARTIAL_CONTENT => {}
+            status if status.is_success() => {
+                if downloaded > 0 {
+                    info!("Server doesn't support resuming, starting over");
+                    file.set_len(0)
+                        .wrap_err("Could not truncate download file")?;
+                    downloaded = 0;
+                }
+            }
+            status => bail!("Non-success status code {status}"),
+        }
+
+        let mut buffer = vec![0; 64 * 1024];
+        let mut last_progress_log = Instant::now();
+
+        loop {
+            let read = resp
+                .read(&mut buffer)
+                .wrap_err("Could not download new version")?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])
+                .wrap_err("Could not write download file")?;
+            downloaded += read as u64;
+
+            if last_progress_log.elapsed() >= PROGRESS_LOG_INTERVAL {
+                last_progress_log = Instant::now();
+                info!(downloaded, total = asset.size, "Downloading new version...");
+            }
+        }
+        file.flush().wrap_err("Could not write download file")?;
+    }
+    // The file has to be closed before it can be deleted
+    drop(file);
+
+    if downloaded != asset.size {
+        remove_download_file(path);
+        bail!(
+            "Downloaded {downloaded} bytes, but the release has {} bytes",
+            asset.size
+        );
     }
 
-    resp.bytes().wrap_err("Could not download new version")
+    let sha256 = sha256_file(path).wrap_err("Could not hash download file")?;
+    if !sha256.eq_ignore_ascii_case(expected_sha256) {
+        remove_download_file(path);
+        return Err(eyre!("Checksum of the download doesn't match the release"))
+            .with_section(|| expected_sha256.to_owned().header("Expected"))
+            .with_section(|| sha256.header("Actual"));
+    }
+
+    info!(downloaded, "Downloaded and verified new version");
+
+    Ok(())
 }
 
 #[instrument]
 fn prepare_target_file() -> Result<PathBuf> {
     let current_file = std::env::current_exe().wrap_err("Failed to get current exe path")?;
+    let current_file = strip_verbatim_prefix(&current_file);
+    let old_file = old_file_path(&current_file)?;
 
+    match std::fs::remove_file(&old_file) {
+        Ok(()) => (),
+        Err(err) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => {
+            return Err(err)
+                .wrap_err("Failed to delete old version")
+                .section(format!("{old_file:?}").header("File path"));
+        }
+    }
+
+    std::fs::rename(&current_file, &old_file).map_err(|err| {
+        let not_writable = err.kind() == ErrorKind::PermissionDenied;
+        let err = Report::new(err)
+            .wrap_err("Failed to move current version")
+            .with_section(|| format!("{current_file:?}").header("Current file"))
+            .with_section(|| format!("{old_file:?}").header("Target file"));
+
+        match (not_writable, current_file.parent()) {
+            (true, Some(dir)) => err.wrap_err(ExeDirNotWritable {
+                dir: dir.to_owned(),
+            }),
+            _ => err,
+        }
+    })?;
+
+    Ok(current_file)
+}
+
+/// `installer.exe` becomes `installer.old.exe`, next to it.
+fn old_file_path(current_file: &Path) -> Result<PathBuf> {
     let mut file_name = current_file
         .file_stem()
         .or(current_file.file_name())
@@ -128,22 +462,407 @@ fn prepare_target_file() -> Result<PathBuf> {
         file_name.push(".");
         file_name.push(ext);
     }
-    let old_file = current_file.with_file_name(file_name);
+    Ok(current_file.with_file_name(file_name))
+}
 
-    match std::fs::remove_file(&old_file) {
-        Ok(()) => (),
-        Err(err) if err.kind() == ErrorKind::NotFound => (),
-        Err(err) => {
-            return Err(err)
-                .wrap_err("Failed to delete old version")
-                .section(format!("{old_file:?}").header("File path"));
+/// Longest path that works without the `\\?\` prefix.
+const MAX_PATH: usize = 260;
+
+/// `current_exe` can return a verbatim path like `\\?\C:\...`, which some APIs handle differently.
+/// The prefix is removed if the path works without it, so the replacement gets spawned with a plain path.
+fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    let plain = without_verbatim_prefix(path);
+    if plain.as_os_str().len() < MAX_PATH {
+        if plain != path {
+            debug!(?path, ?plain, "Stripped verbatim prefix");
         }
+        plain
+    } else {
+        path.to_owned()
     }
+}
 
-    std::fs::rename(&current_file, &old_file)
-        .wrap_err("Failed to move current version")
-        .with_section(|| format!("{current_file:?}").header("Current file"))
-        .with_section(|| format!("{old_file:?}").header("Target file"))?;
+/// The installer lives in a directory it can't write to, e.g. `Program Files`, so it can't replace itself.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("The directory of the installer isn't writable: {dir:?}")]
+pub struct ExeDirNotWritable {
+    pub dir: PathBuf,
+}
 
-    Ok(current_file)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Response, TestServer, unreachable_url};
+    use sha2::{Digest, Sha256};
+    use std::fmt::Write as _;
+
+    fn version(version_string: &str) -> AppVersion {
+        parse_version_string(version_string).unwrap()
+    }
+
+    #[test]
+    fn parses_short_tag() {
+        assert_eq!(
+            version("v1.2"),
+            AppVersion {
+                numbers: vec![1, 2, 0],
+                is_release: true,
+            }
+        );
+        assert_eq!(version("v1.2"), version("1.2.0"));
+    }
+
+    #[test]
+    fn pre_release_is_older_than_release() {
+        assert_eq!(
+            version("v1.2.3-rc1"),
+            AppVersion {
+                numbers: vec![1, 2, 3],
+                is_release: false,
+            }
+        );
+        assert!(version("1.2.3-rc1") < version("1.2.3"));
+        assert!(version("1.2.3-rc1") > version("1.2.2"));
+    }
+
+    #[test]
+    fn parses_four_parts() {
+        assert_eq!(version("1.2.3.4").numbers, [1, 2, 3, 4]);
+        assert_eq!(version("1.2.3.0"), version("1.2.3"));
+        assert!(version("1.2.3.4") > version("1.2.3"));
+        assert!(version("1.2.3.4") < version("1.2.4"));
+    }
+
+    #[test]
+    fn rejects_invalid_versions() {
+        for version_string in ["", "v", "1.x", "1..2", "latest"] {
+            assert!(
+                parse_version_string(version_string).is_err(),
+                "{version_string}"
+            );
+        }
+    }
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_owned(),
+            browser_download_url: format!("https://example.com/{name}"),
+            size: 1,
+            digest: None,
+        }
+    }
+
+    fn picked_name(assets: &[ReleaseAsset], current_stem: Option<&str>) -> Option<String> {
+        pick_exe_asset(assets, current_stem).map(|asset| asset.name.clone())
+    }
+
+    #[test]
+    fn picks_exe_named_like_running_exe() {
+        let assets = [
+            asset("checksums.txt"),
+            asset("OriRandoInstaller-x86.exe"),
+            asset("OriRandoInstaller.exe"),
+            asset("OriRandoInstaller.zip"),
+        ];
+        assert_eq!(
+            picked_name(&assets, Some("orirandoinstaller")).as_deref(),
+            Some("OriRandoInstaller.exe")
+        );
+        assert_eq!(
+            picked_name(&assets, Some("OriRandoInstaller-x86")).as_deref(),
+            Some("OriRandoInstaller-x86.exe")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_exe() {
+        let assets = [
+            asset("OriRandoInstaller.zip"),
+            asset("OriRandoInstaller-x86.EXE"),
+            asset("OriRandoInstaller.exe"),
+        ];
+        assert_eq!(
+            picked_name(&assets, Some("renamed installer")).as_deref(),
+            Some("OriRandoInstaller-x86.EXE")
+        );
+        assert_eq!(
+            picked_name(&assets, None).as_deref(),
+            Some("OriRandoInstaller-x86.EXE")
+        );
+    }
+
+    #[test]
+    fn no_exe_asset() {
+        let assets = [asset("OriRandoInstaller.zip"), asset("exe")];
+        assert_eq!(picked_name(&assets, Some("exe")), None);
+        assert_eq!(picked_name(&[], None), None);
+    }
+
+    #[test]
+    fn old_file_path_keeps_extension() {
+        assert_eq!(
+            old_file_path(Path::new(r"C:\Tools\Ori Rando Installer.exe")).unwrap(),
+            PathBuf::from(r"C:\Tools\Ori Rando Installer.old.exe")
+        );
+        assert_eq!(
+            old_file_path(Path::new(r"C:\Tools\installer")).unwrap(),
+            PathBuf::from(r"C:\Tools\installer.old")
+        );
+    }
+
+    #[test]
+    fn old_file_path_of_verbatim_path() {
+        assert_eq!(
+            old_file_path(Path::new(r"\\?\C:\Tools\installer.exe")).unwrap(),
+            PathBuf::from(r"\\?\C:\Tools\installer.old.exe")
+        );
+    }
+
+    #[test]
+    fn strips_verbatim_prefix_of_short_paths() {
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\C:\Tools\installer.exe")),
+            PathBuf::from(r"C:\Tools\installer.exe")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\UNC\server\share\installer.exe")),
+            PathBuf::from(r"\\server\share\installer.exe")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"C:\Tools\installer.exe")),
+            PathBuf::from(r"C:\Tools\installer.exe")
+        );
+    }
+
+    #[test]
+    fn keeps_verbatim_prefix_of_long_paths() {
+        let long = format!(r"\\?\C:\{}\installer.exe", "a".repeat(MAX_PATH));
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(&long)),
+            PathBuf::from(&long)
+        );
+    }
+
+    fn release_json(tag: &str, download_url: &str) -> String {
+        serde_json::json!({
+            "tag_name": tag,
+            "assets": [{
+                "name": "OriRandoInstaller.exe",
+                "browser_download_url": download_url,
+                "size": 3,
+                "digest": format!("sha256:{}", "0".repeat(64)),
+            }],
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn finds_newer_release() {
+        let server = TestServer::start([(
+            "/latest",
+            Response::new(
+                200,
+                "application/json",
+                release_json("v999.0.0", "https://example.com/new.exe"),
+            ),
+        )]);
+
+        let new_version = new_version_asset_from(&server.url("/latest"))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(new_version.tag, "v999.0.0");
+        assert_eq!(new_version.asset.name, "OriRandoInstaller.exe");
+        assert_eq!(new_version.asset.size, 3);
+        assert!(new_version.asset.digest.is_some());
+        assert_eq!(
+            server.requests()[0].headers["accept"],
+            "application/vnd.github+json"
+        );
+    }
+
+    #[test]
+    fn ignores_older_release() {
+        let server = TestServer::start([(
+            "/latest",
+            Response::new(
+                200,
+                "application/json",
+                release_json("v0.0.1", "https://example.com/old.exe"),
+            ),
+        )]);
+
+        assert!(
+            new_version_asset_from(&server.url("/latest"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn release_query_errors() {
+        let server = TestServer::start([
+            ("/unavailable", Response::new(503, "text/html", "")),
+            (
+                "/malformed",
+                Response::new(200, "application/json", "{\"tag_name\":"),
+            ),
+            (
+                "/no-exe",
+                Response::new(
+                    200,
+                    "application/json",
+                    r#"{"tag_name": "v999.0.0", "assets": []}"#,
+                ),
+            ),
+            (
+                "/bad-tag",
+                Response::new(200, "application/json", release_json("nightly", "")),
+            ),
+        ]);
+
+        for path in ["/unavailable", "/malformed", "/no-exe", "/bad-tag"] {
+            assert!(new_version_asset_from(&server.url(path)).is_err(), "{path}");
+        }
+        assert!(new_version_asset_from(&unreachable_url()).is_err());
+    }
+
+    /// A path in the temp dir that doesn't exist yet and is unique to the test.
+    fn temp_download_path(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ori-rando-installer-test-{}-{test}.part",
+            std::process::id()
+        ));
+        remove_download_file(&path);
+        path
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        Sha256::digest(data)
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+
+    fn served_asset(server: &TestServer, data: &[u8]) -> ReleaseAsset {
+        ReleaseAsset {
+            name: "OriRandoInstaller.exe".to_owned(),
+            browser_download_url: server.url("/exe"),
+            size: data.len() as u64,
+            digest: Some(format!("sha256:{}", sha256_hex(data))),
+        }
+    }
+
+    #[test]
+    fn downloads_and_verifies_exe() {
+        let exe = b"new installer".to_vec();
+        let server = TestServer::start([(
+            "/exe",
+            Response::new(200, "application/octet-stream", exe.clone()),
+        )]);
+        let asset = served_asset(&server, &exe);
+        let path = temp_download_path("verified");
+
+        download_to_file(
+            &Client::new(),
+            &asset,
+            &path,
+            expected_sha256(&asset).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), exe);
+        remove_download_file(&path);
+    }
+
+    #[test]
+    fn starts_over_if_server_ignores_range() {
+        let exe = b"new installer".to_vec();
+        let server = TestServer::start([(
+            "/exe",
+            Response::new(200, "application/octet-stream", exe.clone()),
+        )]);
+        let asset = served_asset(&server, &exe);
+        let path = temp_download_path("resumed");
+        std::fs::write(&path, b"new").unwrap();
+
+        download_to_file(
+            &Client::new(),
+            &asset,
+            &path,
+            expected_sha256(&asset).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(server.requests()[0].headers["range"], "bytes=3-");
+        assert_eq!(std::fs::read(&path).unwrap(), exe);
+        remove_download_file(&path);
+    }
+
+    #[test]
+    fn deletes_download_with_wrong_checksum() {
+        let exe = b"new installer".to_vec();
+        let server = TestServer::start([(
+            "/exe",
+            Response::new(200, "application/octet-stream", b"bad installer".to_vec()),
+        )]);
+        let asset = served_asset(&server, &exe);
+        let path = temp_download_path("tampered");
+
+        assert!(
+            download_to_file(
+                &Client::new(),
+                &asset,
+                &path,
+                expected_sha256(&asset).unwrap()
+            )
+            .is_err()
+        );
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn deletes_download_with_wrong_size() {
+        let exe = b"new installer".to_vec();
+        let server = TestServer::start([(
+            "/exe",
+            Response::new(200, "application/octet-stream", b"new".to_vec()),
+        )]);
+        let asset = served_asset(&server, &exe);
+        let path = temp_download_path("truncated");
+
+        assert!(
+            download_to_file(
+                &Client::new(),
+                &asset,
+                &path,
+                expected_sha256(&asset).unwrap()
+            )
+            .is_err()
+        );
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn keeps_partial_download_on_error_status() {
+        let exe = b"new installer".to_vec();
+        let server = TestServer::start([("/exe", Response::new(503, "text/html", ""))]);
+        let asset = served_asset(&server, &exe);
+        let path = temp_download_path("unavailable");
+        std::fs::write(&path, b"new").unwrap();
+
+        assert!(
+            download_to_file(
+                &Client::new(),
+                &asset,
+                &path,
+                expected_sha256(&asset).unwrap()
+            )
+            .is_err()
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        remove_download_file(&path);
+    }
 }
@@ -1,14 +1,21 @@
+use crate::dll_classifier::RandoVersion;
+use crate::dll_management::BackupLocation;
 use crate::game::GameDir;
-use color_eyre::Result;
+use crate::lang::Language;
+use crate::logging::LogLevel;
+use crate::orirando::{DEFAULT_DLL_MIRRORS, VersionCache};
 use color_eyre::eyre::{Context, ContextCompat};
+use color_eyre::{Report, Result};
 use eframe::egui::ThemePreference;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
-use std::sync::{LazyLock, mpsc};
+use std::sync::{LazyLock, Mutex, mpsc};
+use std::time::SystemTime;
 use std::{env, thread};
-use tracing::{debug, error, info_span, instrument};
+use tracing::{debug, error, info_span, instrument, warn};
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
@@ -18,6 +25,40 @@ pub struct Settings {
     pub game_dir: GameDir,
     pub launch_type: LaunchType,
     pub self_update: bool,
+    pub language: Language,
+    pub minimize_to_tray: bool,
+    /// Keep the window above other windows, e.g. to follow a setup guide side by side.
+    pub always_on_top: bool,
+    /// Launch the game once the randomizer was installed or updated.
+    pub launch_after_install: bool,
+    /// Launch the game once a different version was switched to.
+    pub launch_after_switch: bool,
+    /// Only show what's needed to install and update the randomizer.
+    pub compact_mode: bool,
+    /// Show internals like paths, hashes and classifications, for debugging and support.
+    pub developer_mode: bool,
+    /// Swap vanilla back in when the app is closed, so the game is vanilla while not playing rando.
+    pub restore_vanilla_on_exit: bool,
+    /// The welcome shown on first launch was dismissed for good.
+    pub seen_welcome: bool,
+    pub rando_only_versions: bool,
+    pub dll_mirrors: Vec<String>,
+    pub max_backups: usize,
+    pub backup_location: BackupLocation,
+    /// Custom accent color as RGB, `None` for the default colors of the theme.
+    pub accent_color: Option<[u8; 3]>,
+    /// Show statuses in colors that don't rely on telling red and green apart.
+    pub color_blind_palette: bool,
+    pub log_level: LogLevel,
+    pub version_cache: Option<VersionCache>,
+    /// While this version is installed, updates aren't offered.
+    pub pinned_version: Option<RandoVersion>,
+    /// Minutes between update checks while the app is open, 0 only checks on startup.
+    pub update_check_interval: u64,
+    /// Time of the last successful version check, shown next to the update status.
+    pub last_version_check: Option<SystemTime>,
+    /// Last directory picked in the game dir dialog, even if it wasn't a valid game dir.
+    pub last_browsed_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -33,6 +74,30 @@ impl Default for Settings {
             game_dir: GameDir::default(),
             launch_type: LaunchType::Steam,
             self_update: true,
+            language: Language::English,
+            minimize_to_tray: false,
+            always_on_top: false,
+            launch_after_install: false,
+            launch_after_switch: false,
+            compact_mode: false,
+            developer_mode: false,
+            restore_vanilla_on_exit: false,
+            seen_welcome: false,
+            rando_only_versions: false,
+            dll_mirrors: DEFAULT_DLL_MIRRORS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            max_backups: 5,
+            backup_location: BackupLocation::Managed,
+            accent_color: None,
+            color_blind_palette: false,
+            log_level: LogLevel::Debug,
+            version_cache: None,
+            pinned_version: None,
+            update_check_interval: 60,
+            last_version_check: None,
+            last_browsed_dir: None,
         }
     }
 }
@@ -59,6 +124,7 @@ impl Settings {
             }
             Err(err) => {
                 error!(?err, "Error saving settings");
+                report_save_error(&err);
                 false
             }
         }
@@ -95,20 +161,40 @@ impl Settings {
     }
 }
 
+/// Environment variable pointing at a settings file to use instead of the one in the app data,
+/// e.g. for testing with a throwaway config.
+const CONFIG_PATH_VAR: &str = "ORI_RANDO_INSTALLER_CONFIG";
+
 impl Settings {
     fn save_path() -> Result<PathBuf> {
-        let local_appdata =
-            env::var_os("LOCALAPPDATA").wrap_err("Error retrieving %LOCALAPPDATA%")?;
-
-        let mut settings_path = PathBuf::from(local_appdata);
-        settings_path.extend(["Ori DE Randomizer", "settings.toml"]);
+        match env::var_os(CONFIG_PATH_VAR) {
+            Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+            _ => Ok(app_data_dir()?.join("settings.toml")),
+        }
+    }
 
-        Ok(settings_path)
+    /// Backup of the last successfully saved settings, used if the settings file is corrupt.
+    fn backup_path(save_path: &Path) -> PathBuf {
+        save_path.with_extension("toml.bak")
     }
 
     #[instrument]
     fn try_load() -> Result<Self> {
         let path = Self::save_path()?;
+
+        Self::try_load_from(&path).or_else(|err| {
+            let backup_path = Self::backup_path(&path);
+            if !backup_path.exists() {
+                return Err(err);
+            }
+
+            warn!(?err, "Error loading settings, trying backup");
+            Self::try_load_from(&backup_path).wrap_err("Error loading settings backup")
+        })
+    }
+
+    #[instrument]
+    fn try_load_from(path: &Path) -> Result<Self> {
         let contents = std::fs::read_to_string(path).wrap_err("Error reading settings file")?;
         let settings = toml::from_str(&contents).wrap_err("Error parsing settings")?;
 
@@ -117,6 +203,8 @@ impl Settings {
         Ok(settings)
     }
 
+    /// Writes to a temporary file first and renames it over the settings file,
+    /// so an interrupted save never leaves a truncated settings file behind.
     #[instrument(skip(self))]
     fn try_save(&self) -> Result<()> {
         let contents = toml::to_string(self).wrap_err("Error serializing settings")?;
@@ -125,12 +213,45 @@ impl Settings {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).wrap_err("Error creating settings directory")?;
         }
-        std::fs::write(path, contents).wrap_err("Error writing settings")?;
+
+        // Unique per process, so concurrent instances don't write into the same temporary file
+        let temp_path = path.with_extension(format!("toml.{}.tmp", std::process::id()));
+        std::fs::write(&temp_path, &contents).wrap_err("Error writing settings")?;
+        std::fs::rename(&temp_path, &path).wrap_err("Error replacing settings file")?;
+
+        if let Err(err) = std::fs::write(Self::backup_path(&path), contents) {
+            warn!(?err, "Error updating settings backup");
+        }
 
         Ok(())
     }
 }
 
+/// Directory for everything the app stores, below %LOCALAPPDATA%.
+pub fn app_data_dir() -> Result<PathBuf> {
+    let local_appdata = env::var_os("LOCALAPPDATA").wrap_err("Error retrieving %LOCALAPPDATA%")?;
+    Ok(PathBuf::from(local_appdata).join("Ori DE Randomizer"))
+}
+
+static SAVE_ERROR: Mutex<Option<String>> = Mutex::new(None);
+static SAVE_ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Remembers the first failed save, so the user can be told why settings don't persist.
+/// Later failures most likely have the same cause and are only logged.
+fn report_save_error(err: &Report) {
+    if SAVE_ERROR_REPORTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let path = Settings::save_path().unwrap_or_default();
+    *SAVE_ERROR.lock().unwrap() = Some(format!("{}\n{err:#}", path.display()));
+}
+
+/// Takes the error of the first failed save, if there was one and it wasn't taken before.
+pub fn take_save_error() -> Option<String> {
+    SAVE_ERROR.lock().unwrap().take()
+}
+
 impl Display for LaunchType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
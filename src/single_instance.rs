@@ -0,0 +1,77 @@
+use crate::gui::WINDOW_TITLE;
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, instrument};
+use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ALREADY_EXISTS, GetLastError, HANDLE};
+use windows_sys::Win32::System::Threading::CreateMutexW;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    FindWindowW, SW_RESTORE, SetForegroundWindow, ShowWindow,
+};
+
+const MUTEX_NAME: &str = r"Local\ori-de-randomizer-single-instance";
+
+/// Held for as long as this is the only running instance.
+pub struct InstanceGuard(HANDLE);
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
+
+/// Returns `None` if another instance is still running after waiting for `wait`.
+///
+/// If the named mutex can't be created at all, this doesn't block startup and returns a no-op guard.
+#[instrument]
+pub fn acquire_instance(wait: Duration) -> Option<InstanceGuard> {
+    let name = to_wide(MUTEX_NAME);
+    let deadline = Instant::now() + wait;
+
+    loop {
+        let handle = unsafe { CreateMutexW(ptr::null(), 0, name.as_ptr()) };
+        if handle.is_null() {
+            error!(
+                error_code = unsafe { GetLastError() },
+                "Couldn't create instance mutex"
+            );
+            return Some(InstanceGuard(handle));
+        }
+
+        if unsafe { GetLastError() } != ERROR_ALREADY_EXISTS {
+            return Some(InstanceGuard(handle));
+        }
+
+        unsafe { CloseHandle(handle) };
+
+        if Instant::now() >= deadline {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Brings the window of the already running instance to the foreground.
+#[instrument]
+pub fn focus_existing_window() {
+    let title = to_wide(WINDOW_TITLE);
+    let hwnd = unsafe { FindWindowW(ptr::null(), title.as_ptr()) };
+
+    if hwnd.is_null() {
+        info!("Window of the existing instance not found");
+        return;
+    }
+
+    unsafe {
+        ShowWindow(hwnd, SW_RESTORE);
+        SetForegroundWindow(hwnd);
+    }
+}
+
+fn to_wide(string: &str) -> Vec<u16> {
+    OsStr::new(string).encode_wide().chain([0]).collect()
+}
@@ -1,29 +1,107 @@
-use color_eyre::eyre::{OptionExt, WrapErr, bail, eyre};
-use std::path::PathBuf;
+use color_eyre::eyre::{OptionExt, WrapErr, bail};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use winreg::RegKey;
-use winreg::enums::HKEY_CLASSES_ROOT;
 
-use color_eyre::{Result, Section, SectionExt};
+use color_eyre::Result;
 use regex::Regex;
 use tracing::{debug, instrument};
 
+/// Launches through the steam protocol, which also works for games running under Proton.
 #[instrument]
 pub fn launch_game(app_id: &str) -> Result<()> {
     opener::open(format!("steam://rungameid/{app_id}")).wrap_err("Error opening steam link")
 }
 
+/// Opens steam's "Verify integrity of game files" for the app.
 #[instrument]
-pub fn get_game_dir(app_id: &str) -> Result<PathBuf> {
-    let steam_dir = get_steam_dir().wrap_err("Getting steam dir")?;
+pub fn validate_game(app_id: &str) -> Result<()> {
+    opener::open(format!("steam://validate/{app_id}")).wrap_err("Error opening steam link")
+}
+
+/// Whether steam can be found, which is required to launch games through it.
+#[instrument]
+pub fn is_steam_installed() -> bool {
+    !get_steam_dirs().is_empty()
+}
+
+/// Install dirs of the app in every steam dir it's found in, in the order of [`get_steam_dirs`].
+#[instrument]
+pub fn get_game_dirs(app_id: &str) -> Result<Vec<PathBuf>> {
+    game_dirs_in(get_steam_dirs(), app_id)
+}
+
+fn game_dirs_in(steam_dirs: Vec<PathBuf>, app_id: &str) -> Result<Vec<PathBuf>> {
+    let game_dirs: Vec<_> = steam_dirs
+        .into_iter()
+        .filter_map(|steam_dir| {
+            get_game_dir_in(steam_dir.clone(), app_id)
+                .inspect_err(|err| debug!(?steam_dir, ?err, "App not found in steam dir"))
+                .ok()
+        })
+        .collect();
+
+    if game_dirs.is_empty() {
+        bail!("App not found in any steam dir");
+    }
+    Ok(game_dirs)
+}
+
+fn get_game_dir_in(steam_dir: PathBuf, app_id: &str) -> Result<PathBuf> {
     let library_dir = get_library_for(steam_dir, app_id).wrap_err("Getting game library")?;
     let game_dir =
         get_game_install_dir(library_dir, app_id).wrap_err("Getting game install dir")?;
     Ok(game_dir)
 }
 
+/// Some machines have several steam installs, e.g. a leftover one, and the one registered for the
+/// `steam` protocol isn't necessarily the one the game is in. So every steam dir that can be found is returned,
+/// the one of the protocol handler first.
+#[cfg(windows)]
+#[instrument]
+fn get_steam_dirs() -> Vec<PathBuf> {
+    use winreg::RegKey;
+    use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+    let registry_values = [
+        (HKEY_CURRENT_USER, r"Software\Valve\Steam", "SteamPath"),
+        (
+            HKEY_LOCAL_MACHINE,
+            r"SOFTWARE\WOW6432Node\Valve\Steam",
+            "InstallPath",
+        ),
+        (HKEY_LOCAL_MACHINE, r"SOFTWARE\Valve\Steam", "InstallPath"),
+    ];
+
+    let mut candidates = Vec::new();
+    match get_protocol_steam_dir() {
+        Ok(dir) => candidates.push(dir),
+        Err(err) => debug!(?err, "No steam protocol handler"),
+    }
+    for (hkey, path, name) in registry_values {
+        let value = RegKey::predef(hkey)
+            .open_subkey(path)
+            .and_then(|key| key.get_value::<String, _>(name));
+        match value {
+            Ok(dir) => candidates.push(PathBuf::from(dir)),
+            Err(err) => debug!(?err, path, name, "Steam registry value not found"),
+        }
+    }
+    if let Some(program_files) = std::env::var_os("ProgramFiles(x86)") {
+        candidates.push(PathBuf::from(program_files).join("Steam"));
+    }
+
+    existing_unique_dirs(candidates)
+}
+
+/// The steam dir of the steam registered for the `steam` protocol.
+#[cfg(windows)]
 #[instrument]
-fn get_steam_dir() -> Result<PathBuf> {
+fn get_protocol_steam_dir() -> Result<PathBuf> {
+    use color_eyre::eyre::eyre;
+    use color_eyre::{Section, SectionExt};
+    use winreg::RegKey;
+    use winreg::enums::HKEY_CLASSES_ROOT;
+
     let hkcr = RegKey::predef(HKEY_CLASSES_ROOT);
     let steam_key = hkcr.open_subkey("steam").wrap_err("Opening HKCR\\steam")?;
 
@@ -60,23 +138,88 @@ fn get_steam_dir() -> Result<PathBuf> {
     Ok(steam_dir)
 }
 
+/// Without a registry, look for steam in the locations used by the native and flatpak linux clients.
+#[cfg(not(windows))]
+#[instrument]
+fn get_steam_dirs() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME") else {
+        debug!("No $HOME, can't look for steam");
+        return Vec::new();
+    };
+    let home = PathBuf::from(home);
+
+    let candidates = vec![
+        home.join(".steam/steam"),
+        home.join(".local/share/Steam"),
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+    ];
+
+    existing_unique_dirs(candidates)
+}
+
+/// Keeps the candidates that are steam dirs, without duplicates, e.g. through symlinks or different casing.
+fn existing_unique_dirs(candidates: Vec<PathBuf>) -> Vec<PathBuf> {
+    unique_dirs(candidates, |candidate| {
+        if !candidate.join("steamapps/libraryfolders.vdf").is_file() {
+            return None;
+        }
+        std::fs::canonicalize(candidate).ok()
+    })
+}
+
+/// Keeps the first of the candidates that `resolve` to the same path, in their original order.
+/// Candidates that don't resolve at all are dropped.
+fn unique_dirs(
+    candidates: Vec<PathBuf>,
+    resolve: impl Fn(&Path) -> Option<PathBuf>,
+) -> Vec<PathBuf> {
+    let mut seen = Vec::new();
+    let mut dirs = Vec::new();
+    for candidate in candidates {
+        let Some(resolved) = resolve(&candidate) else {
+            continue;
+        };
+        if !seen.contains(&resolved) {
+            debug!(?candidate, "Found steam dir");
+            seen.push(resolved);
+            dirs.push(candidate);
+        }
+    }
+    dirs
+}
+
 static LIBRARY_PATH: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"^\s*"path"\s*"([^"]+)"$"#).unwrap());
 
 static LIBRARY_APP: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"^\s*"([0-9]+)"\s*"[0-9]+"$"#).unwrap());
 
+/// Library entry of the legacy flat format, e.g. `"1" "D:\\SteamLibrary"`.
+/// The value has to contain a non-digit, to not confuse it with an app entry of the modern format.
+static LEGACY_LIBRARY_PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*"[0-9]+"\s*"([^"]*[^"0-9][^"]*)"$"#).unwrap());
+
 #[instrument]
 fn get_library_for(steam_dir: PathBuf, app_id: &str) -> Result<PathBuf> {
-    let mut vdf_path = steam_dir;
+    let mut vdf_path = steam_dir.clone();
     vdf_path.extend(["steamapps", "libraryfolders.vdf"]);
     let vdf = std::fs::read_to_string(vdf_path).wrap_err("reading libraryfolders.vdf")?;
 
+    if vdf.lines().any(|line| LIBRARY_PATH.is_match(line)) {
+        find_library_in_vdf(&vdf, app_id)
+    } else {
+        debug!("libraryfolders.vdf uses the legacy format");
+        find_legacy_library(steam_dir, &vdf, app_id)
+    }
+}
+
+/// Modern format: each library lists its path and the apps installed in it.
+fn find_library_in_vdf(vdf: &str, app_id: &str) -> Result<PathBuf> {
     let mut current_library = None;
     for line in vdf.lines() {
         if let Some(captures) = LIBRARY_PATH.captures(line) {
             let (_full, [path]) = captures.extract();
-            current_library = Some(path.replace(r"\\", r"\"));
+            current_library = Some(unescape_vdf_path(path));
         } else if let Some(captures) = LIBRARY_APP.captures(line) {
             let (_full, [app_str]) = captures.extract();
             if app_str == app_id {
@@ -91,6 +234,33 @@ fn get_library_for(steam_dir: PathBuf, app_id: &str) -> Result<PathBuf> {
     bail!("App not found in libraryfolders.vdf");
 }
 
+/// Legacy format: only the paths of additional libraries are listed, the steam dir itself is implicitly a library.
+/// Whether an app is installed in a library is determined by the existence of its app manifest.
+fn find_legacy_library(steam_dir: PathBuf, vdf: &str, app_id: &str) -> Result<PathBuf> {
+    let additional_libraries = vdf.lines().filter_map(|line| {
+        let captures = LEGACY_LIBRARY_PATH.captures(line)?;
+        let (_full, [path]) = captures.extract();
+        Some(PathBuf::from(unescape_vdf_path(path)))
+    });
+
+    for library in [steam_dir].into_iter().chain(additional_libraries) {
+        let manifest_path = library
+            .join("steamapps")
+            .join(format!("appmanifest_{app_id}.acf"));
+        if manifest_path.is_file() {
+            debug!(?library, "Found app manifest in library");
+            return Ok(library);
+        }
+    }
+
+    bail!("App not found in any library of the legacy libraryfolders.vdf");
+}
+
+/// Unescapes backslashes. Linux paths don't contain any, so this is a no-op there.
+fn unescape_vdf_path(path: &str) -> String {
+    path.replace(r"\\", r"\")
+}
+
 static INSTALL_DIR: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"(?m)^\s*"installdir"\s*"([^"]+)"$"#).unwrap());
 
@@ -112,3 +282,170 @@ fn get_game_install_dir(library_dir: PathBuf, app_id: &str) -> Result<PathBuf> {
 
     bail!("installdir not found in app manifest")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::collections::HashMap;
+    use std::fmt::Write;
+
+    const APP_ID: &str = "387290";
+
+    #[test]
+    fn keeps_two_steam_roots_without_duplicates() {
+        // Two installs, one registered twice with different casing, and a leftover registry value
+        let resolved = HashMap::from([
+            (PathBuf::from("C:/Program Files (x86)/Steam"), "c:/steam"),
+            (PathBuf::from("c:/program files (x86)/steam"), "c:/steam"),
+            (PathBuf::from("D:/Steam"), "d:/steam"),
+        ]);
+        let candidates = vec![
+            PathBuf::from("D:/Steam"),
+            PathBuf::from("C:/Program Files (x86)/Steam"),
+            PathBuf::from("E:/Old Steam"),
+            PathBuf::from("c:/program files (x86)/steam"),
+            PathBuf::from("D:/Steam"),
+        ];
+
+        let dirs = unique_dirs(candidates, |candidate| {
+            resolved.get(candidate).map(PathBuf::from)
+        });
+
+        assert_eq!(
+            dirs,
+            [
+                PathBuf::from("D:/Steam"),
+                PathBuf::from("C:/Program Files (x86)/Steam"),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_steam_roots() {
+        assert!(unique_dirs(vec![PathBuf::from("C:/Steam")], |_| None).is_empty());
+        assert!(unique_dirs(Vec::new(), |candidate| Some(candidate.to_owned())).is_empty());
+    }
+
+    fn escape_vdf_path(path: &Path) -> String {
+        path.to_str().unwrap().replace('\\', r"\\")
+    }
+
+    /// Writes `libraryfolders.vdf` in the legacy format, which only lists the additional libraries.
+    fn write_legacy_vdf(steam_dir: &Path, libraries: &[&Path]) {
+        let mut vdf = String::from(concat!(
+            "\"LibraryFolders\"\n{\n",
+            "\t\"TimeNextStatsReport\"\t\t\"1700000000\"\n",
+            "\t\"ContentStatsID\"\t\t\"-4242\"\n",
+        ));
+        for (i, library) in libraries.iter().enumerate() {
+            _ = writeln!(vdf, "\t\"{}\"\t\t\"{}\"", i + 1, escape_vdf_path(library));
+        }
+        vdf += "}\n";
+
+        let steamapps = steam_dir.join("steamapps");
+        std::fs::create_dir_all(&steamapps).unwrap();
+        std::fs::write(steamapps.join("libraryfolders.vdf"), vdf).unwrap();
+    }
+
+    fn write_app_manifest(library: &Path) {
+        let steamapps = library.join("steamapps");
+        std::fs::create_dir_all(&steamapps).unwrap();
+        std::fs::write(
+            steamapps.join(format!("appmanifest_{APP_ID}.acf")),
+            format!(
+                "\"AppState\"\n{{\n\t\"appid\"\t\t\"{APP_ID}\"\n\t\"installdir\"\t\t\"Ori DE\"\n}}\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    fn game_dir_in(library: &Path) -> PathBuf {
+        library.join("steamapps").join("common").join("Ori DE")
+    }
+
+    #[test]
+    fn finds_app_in_legacy_additional_library() {
+        let dir = TempDir::new("steam-legacy-library");
+        let steam_dir = dir.path().join("Steam");
+        let first = dir.path().join("Library 1");
+        let second = dir.path().join("Library 2");
+        write_legacy_vdf(&steam_dir, &[&first, &second]);
+        write_app_manifest(&second);
+
+        assert_eq!(
+            get_game_dir_in(steam_dir, APP_ID).unwrap(),
+            game_dir_in(&second)
+        );
+    }
+
+    #[test]
+    fn finds_app_in_legacy_steam_dir() {
+        let dir = TempDir::new("steam-legacy-steam-dir");
+        let steam_dir = dir.path().join("Steam");
+        let library = dir.path().join("Library");
+        write_legacy_vdf(&steam_dir, &[&library]);
+        write_app_manifest(&steam_dir);
+        write_app_manifest(&library);
+
+        assert_eq!(
+            get_game_dir_in(steam_dir.clone(), APP_ID).unwrap(),
+            game_dir_in(&steam_dir)
+        );
+    }
+
+    #[test]
+    fn app_missing_from_legacy_libraries() {
+        let dir = TempDir::new("steam-legacy-missing");
+        let steam_dir = dir.path().join("Steam");
+        write_legacy_vdf(&steam_dir, &[&dir.path().join("Library")]);
+
+        assert!(get_game_dir_in(steam_dir, APP_ID).is_err());
+    }
+
+    #[test]
+    fn finds_app_in_second_steam_root() {
+        let first = TempDir::new("steam-root-1");
+        let second = TempDir::new("steam-root-2");
+        write_legacy_vdf(second.path(), &[]);
+        write_app_manifest(second.path());
+        let steam_dirs = vec![first.path().to_owned(), second.path().to_owned()];
+
+        assert_eq!(
+            game_dirs_in(steam_dirs, APP_ID).unwrap(),
+            [game_dir_in(second.path())]
+        );
+        assert!(game_dirs_in(vec![first.path().to_owned()], APP_ID).is_err());
+    }
+
+    #[test]
+    fn finds_app_in_modern_vdf() {
+        let vdf = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"apps"
+		{
+			"228980"		"123456"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+		"apps"
+		{
+			"387290"		"11508430656"
+		}
+	}
+}"#;
+
+        assert_eq!(
+            find_library_in_vdf(vdf, APP_ID).unwrap(),
+            PathBuf::from(r"D:\SteamLibrary")
+        );
+        assert!(find_library_in_vdf(vdf, "1").is_err());
+        // App entries have the same shape as legacy library entries, but only digits as value
+        assert!(!vdf.lines().any(|line| LEGACY_LIBRARY_PATH.is_match(line)));
+    }
+}
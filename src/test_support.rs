@@ -0,0 +1,305 @@
+//! Helpers for tests: a local HTTP server with canned responses, minimal .NET dlls and temporary directories.
+
+use crate::dll_classifier::ByteOrder;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A response of the [`TestServer`], sent with `Content-Length` and `Connection: close`.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", content_type.to_owned())],
+            body: body.into(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_owned()));
+        self
+    }
+}
+
+/// A request the [`TestServer`] received, header names are lowercase.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+/// Serves canned responses by path on localhost until it's dropped, unknown paths get a 404.
+/// Connections are handled one after another, which is plenty for the blocking client.
+pub struct TestServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl TestServer {
+    pub fn start<'a>(routes: impl IntoIterator<Item = (&'a str, Response)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes: HashMap<String, Response> = routes
+            .into_iter()
+            .map(|(path, response)| (path.to_owned(), response))
+            .collect();
+        let requests = Arc::default();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        thread::spawn({
+            let requests = Arc::clone(&requests);
+            let stopped = Arc::clone(&stopped);
+            move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    // A client giving up mid-request isn't the server's problem
+                    if let Ok(stream) = stream {
+                        let _ = handle_connection(stream, &routes, &requests);
+                    }
+                }
+            }
+        });
+
+        Self {
+            addr,
+            requests,
+            stopped,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Wakes up the server thread, which is blocked in `accept`
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+/// An address nothing listens on, for testing connection errors.
+pub fn unreachable_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}/", listener.local_addr().unwrap())
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    routes: &HashMap<String, Response>,
+    requests: &Mutex<Vec<Request>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default().to_owned();
+
+    // Only GET and HEAD are served, so there's no body to read after the headers
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+        }
+    }
+
+    let not_found = Response::new(404, "text/plain", "Not found");
+    let response = routes.get(&path).unwrap_or(&not_found);
+    let is_head = method == "HEAD";
+    requests.lock().unwrap().push(Request {
+        method,
+        path,
+        headers,
+    });
+
+    let mut head = format!("HTTP/1.1 {} Test\r\n", response.status);
+    for (name, value) in &response.headers {
+        _ = write!(head, "{name}: {value}\r\n");
+    }
+    _ = write!(
+        head,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    if !is_head {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+/// A directory in the temp dir that is unique to the test, deleted with its contents when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(test: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "ori-rando-installer-test-{}-{test}",
+            std::process::id()
+        ));
+        // Leftovers of an earlier run with the same process id
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Size of the dlls built here, so they pass the plausibility check of downloads.
+const DLL_SIZE: usize = 1024 * 1024;
+
+/// The game assembly of the Definitive Edition, without the randomizer.
+pub fn vanilla_dll() -> Vec<u8> {
+    dotnet_dll(
+        b"\0SpiritGrenadeDamageDealer\0",
+        Some(&us_heap(&[], ByteOrder::Little)),
+        DLL_SIZE,
+    )
+}
+
+/// The game assembly with the randomizer of `version`, e.g. "4.1.0".
+pub fn rando_dll(version: &str) -> Vec<u8> {
+    dotnet_dll(
+        b"\0SpiritGrenadeDamageDealer\0Randomizer\0",
+        Some(&us_heap(&["Randomizer", version], ByteOrder::Little)),
+        DLL_SIZE,
+    )
+}
+
+/// A `#US` heap with the `strings`. The compiler writes them little endian.
+/// Each entry is the length prefix, the UTF-16 string and the terminal byte.
+pub fn us_heap(strings: &[&str], byte_order: ByteOrder) -> Vec<u8> {
+    // The heap always starts with the empty string
+    let mut heap = vec![0];
+    for string in strings {
+        let mut entry: Vec<u8> = string
+            .encode_utf16()
+            .flat_map(|unit| match byte_order {
+                ByteOrder::Little => unit.to_le_bytes(),
+                ByteOrder::Big => unit.to_be_bytes(),
+            })
+            .collect();
+        entry.push(0);
+        heap.push(u8::try_from(entry.len()).unwrap());
+        heap.extend(entry);
+    }
+    heap
+}
+
+/// A PE32 file with only the parts the parser reads: one section with the CLI header and the metadata.
+/// The metadata has a `#Strings` heap and, if given, a `#US` heap. The file is padded with zeros to `size`.
+pub fn dotnet_dll(strings: &[u8], us: Option<&[u8]>, size: usize) -> Vec<u8> {
+    const PE_OFFSET: usize = 0x80;
+    const OPTIONAL_HEADER: usize = PE_OFFSET + 24;
+    const OPTIONAL_HEADER_SIZE: usize = 224;
+    const SECTION_TABLE: usize = OPTIONAL_HEADER + OPTIONAL_HEADER_SIZE;
+    const SECTION_OFFSET: usize = 0x200;
+    const SECTION_RVA: usize = 0x2000;
+    const CLI_HEADER_SIZE: usize = 0x48;
+
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+    fn le_u32(value: usize) -> [u8; 4] {
+        u32::try_from(value).unwrap().to_le_bytes()
+    }
+
+    let mut streams = vec![(b"#Strings".as_slice(), strings)];
+    if let Some(us) = us {
+        streams.push((b"#US", us));
+    }
+
+    let version = b"v2.0.50727\0\0";
+    let mut metadata = b"BSJB\x01\x00\x01\x00\0\0\0\0".to_vec();
+    metadata.extend(le_u32(version.len()));
+    metadata.extend(version);
+    metadata.extend(0u16.to_le_bytes());
+    metadata.extend(u16::try_from(streams.len()).unwrap().to_le_bytes());
+
+    let stream_headers_size: usize = streams
+        .iter()
+        .map(|(name, _)| 8 + (name.len() + 1).next_multiple_of(4))
+        .sum();
+    let mut stream_offset = metadata.len() + stream_headers_size;
+    for (name, data) in &streams {
+        metadata.extend(le_u32(stream_offset));
+        metadata.extend(le_u32(data.len()));
+        let mut name = name.to_vec();
+        name.resize((name.len() + 1).next_multiple_of(4), 0);
+        metadata.extend(name);
+        stream_offset += data.len();
+    }
+    for (_, data) in &streams {
+        metadata.extend(*data);
+    }
+
+    let mut section = vec![0; CLI_HEADER_SIZE];
+    put(&mut section, 0, &le_u32(CLI_HEADER_SIZE));
+    put(&mut section, 8, &le_u32(SECTION_RVA + CLI_HEADER_SIZE));
+    put(&mut section, 12, &le_u32(metadata.len()));
+    section.extend(metadata);
+
+    let mut dll = vec![0; SECTION_OFFSET];
+    put(&mut dll, 0, b"MZ");
+    put(&mut dll, 60, &le_u32(PE_OFFSET));
+    put(&mut dll, PE_OFFSET, b"PE\0\0");
+    put(&mut dll, PE_OFFSET + 4, &0x14cu16.to_le_bytes());
+    put(&mut dll, PE_OFFSET + 6, &1u16.to_le_bytes());
+    put(
+        &mut dll,
+        PE_OFFSET + 20,
+        &u16::try_from(OPTIONAL_HEADER_SIZE).unwrap().to_le_bytes(),
+    );
+    put(&mut dll, OPTIONAL_HEADER, &0x10bu16.to_le_bytes());
+    // Data directory 14, the CLI header
+    put(&mut dll, OPTIONAL_HEADER + 208, &le_u32(SECTION_RVA));
+    put(&mut dll, OPTIONAL_HEADER + 212, &le_u32(CLI_HEADER_SIZE));
+    put(&mut dll, SECTION_TABLE, b".text\0\0\0");
+    put(&mut dll, SECTION_TABLE + 8, &le_u32(section.len()));
+    put(&mut dll, SECTION_TABLE + 12, &le_u32(SECTION_RVA));
+    put(&mut dll, SECTION_TABLE + 16, &le_u32(section.len()));
+    put(&mut dll, SECTION_TABLE + 20, &le_u32(SECTION_OFFSET));
+    dll.extend(section);
+
+    if dll.len() < size {
+        dll.resize(size, 0);
+    }
+    dll
+}